@@ -0,0 +1,145 @@
+//! Area queries, for finding every entity caught in an attack.
+
+use bevy::prelude::*;
+use bevy::ecs::system::SystemParam;
+use bevy::math::swizzles::*;
+
+use serde::{Deserialize, Serialize};
+
+use super::damage::Dead;
+use super::targeting::{Range, SpatialIndex, Stealth};
+use super::{BoundingCircle, Hostility};
+
+/// Which entities an area query or attack can hit, relative to the hostility
+/// of the attacker.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Hash, Serialize)]
+pub enum HitFilter {
+    /// Only entities the attacker is hostile to.
+    #[default]
+    Hostile,
+    /// Only entities the attacker isn't hostile to.
+    Allied,
+    /// Every entity.
+    All,
+}
+
+impl HitFilter {
+    /// Checks if an entity with `other` hostility can be hit by an attacker
+    /// with `hostility`.
+    pub fn can_hit(&self, hostility: &Hostility, other: &Hostility) -> bool {
+        match self {
+            HitFilter::Hostile => hostility.is_hostile_to(other),
+            HitFilter::Allied => !hostility.is_hostile_to(other),
+            HitFilter::All => true,
+        }
+    }
+}
+
+/// How damage scales with distance from the center of an area.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum Falloff {
+    /// Every entity in the area takes full damage.
+    #[default]
+    None,
+    /// Damage scales linearly from full damage at the center down to `min`
+    /// at the edge of the area.
+    Linear {
+        min: f32,
+    },
+}
+
+impl Falloff {
+    /// The damage multiplier for an entity `distance` away from the center of
+    /// an area with a `radius`.
+    pub fn scale(&self, distance: f32, radius: f32) -> f32 {
+        match self {
+            Falloff::None => 1.0,
+            Falloff::Linear { min } => {
+                if radius <= 0.0 {
+                    return 1.0;
+                }
+
+                let t = (distance / radius).clamp(0.0, 1.0);
+                1.0 + (min - 1.0) * t
+            }
+        }
+    }
+}
+
+/// An entity found by an [`AreaQuery`].
+#[derive(Clone, Copy, Debug)]
+pub struct AreaHit {
+    pub entity: Entity,
+    /// The distance from the center of the area to the entity.
+    pub distance: f32,
+}
+
+/// A system param for finding every entity within a shape.
+///
+/// This uses the [`SpatialIndex`], so it is only accurate after
+/// [`TargetingSystems::SortTargets`][1].
+///
+/// [1]: super::targeting::TargetingSystems::SortTargets
+#[derive(SystemParam)]
+pub struct AreaQuery<'w, 's> {
+    spatial_index: Res<'w, SpatialIndex>,
+    targets_query: Query<'w, 's, (
+        &'static GlobalTransform,
+        &'static BoundingCircle,
+        Option<&'static Hostility>,
+        Option<&'static Stealth>,
+    ), Without<Dead>>,
+}
+
+impl<'w, 's> AreaQuery<'w, 's> {
+    /// Finds every entity whose [`BoundingCircle`] intersects `range` placed
+    /// at `transform`, in [`Hatred`][1] order.
+    ///
+    /// [1]: super::targeting::Hatred
+    pub fn within(
+        &self,
+        range: &Range,
+        transform: &GlobalTransform,
+        hostility: Hostility,
+        filter: HitFilter,
+    ) -> Vec<AreaHit> {
+        let (min, max) = range.bounds(transform);
+        let center = transform.translation().xy();
+
+        self.spatial_index
+            .query(min, max)
+            .into_iter()
+            .filter_map(|entity| {
+                let (target_transform, bounding_circle, target_hostility, stealth) =
+                    self.targets_query.get(entity).ok()?;
+
+                let visible = stealth.map(|s| s.visible).unwrap_or(true);
+                let target_hostility = target_hostility.copied().unwrap_or_default();
+
+                (visible
+                    && filter.can_hit(&hostility, &target_hostility)
+                    && range.intersects(transform, target_transform, bounding_circle))
+                    .then(|| AreaHit {
+                        entity,
+                        distance: center.distance(target_transform.translation().xy()),
+                    })
+            })
+            .collect()
+    }
+
+    /// Shorthand for [`AreaQuery::within`] a circle around a point.
+    pub fn within_radius(
+        &self,
+        center: Vec2,
+        radius: f32,
+        hostility: Hostility,
+        filter: HitFilter,
+    ) -> Vec<AreaHit> {
+        self.within(
+            &Range::circle(radius),
+            &GlobalTransform::from_translation(center.extend(0.0)),
+            hostility,
+            filter,
+        )
+    }
+}
@@ -2,13 +2,16 @@
 
 use bevy::prelude::*;
 
+use std::collections::HashSet;
 use std::time::Duration;
 use std::iter::once;
 
 use crate::stats::{find_stats, stat, ComputedStat};
 
-use super::targeting::{Targets, TargetingSystems};
+use super::area::{AreaQuery, Falloff, HitFilter};
+use super::targeting::{Range, Targets, TargetingSystems};
 use super::damage::{DamageType, DamageReceivedEvent};
+use super::Hostility;
 
 pub struct AutoAttackPlugin;
 
@@ -22,7 +25,12 @@ impl Plugin for AutoAttackPlugin {
                         .before(tick_attack_cycle_timers)
                         .after(TargetingSystems::SearchTargets),
                     tick_attack_cycle_timers,
-                    do_melee_auto_attack
+                    (
+                        do_melee_auto_attack,
+                        do_splash_auto_attack,
+                        do_area_auto_attack,
+                        do_chain_auto_attack,
+                    )
                         .after(tick_attack_cycle_timers)
                         .after(TargetingSystems::SearchTargets),
                 )
@@ -37,6 +45,156 @@ pub struct Melee {
     in_frontswing: bool,
 }
 
+impl Melee {
+    /// Constructs a `Melee` with a [`DamageType`].
+    pub fn with_type(self, damage_type: DamageType) -> Melee {
+        Melee {
+            damage_type,
+            ..self
+        }
+    }
+}
+
+/// An autoattack scheme that damages every entity in a radius around each
+/// target as soon as the frontswing concludes.
+#[derive(Clone, Component, Debug)]
+pub struct Splash {
+    /// The radius around each target that is damaged.
+    pub radius: f32,
+    pub damage_type: DamageType,
+    /// How damage scales with distance from the target.
+    pub falloff: Falloff,
+    /// Which entities are caught in the splash.
+    pub filter: HitFilter,
+    in_frontswing: bool,
+}
+
+impl Splash {
+    /// Creates a new `Splash` with a radius.
+    pub fn new(radius: f32) -> Splash {
+        Splash {
+            radius,
+            damage_type: DamageType::default(),
+            falloff: Falloff::default(),
+            filter: HitFilter::default(),
+            in_frontswing: false,
+        }
+    }
+
+    /// Constructs a `Splash` with a [`DamageType`].
+    pub fn with_type(self, damage_type: DamageType) -> Splash {
+        Splash {
+            damage_type,
+            ..self
+        }
+    }
+
+    /// Constructs a `Splash` with a [`Falloff`].
+    pub fn with_falloff(self, falloff: Falloff) -> Splash {
+        Splash {
+            falloff,
+            ..self
+        }
+    }
+
+    /// Constructs a `Splash` with a [`HitFilter`].
+    pub fn with_filter(self, filter: HitFilter) -> Splash {
+        Splash {
+            filter,
+            ..self
+        }
+    }
+}
+
+/// An autoattack scheme that damages every entity in the entity's own
+/// [`Range`] as soon as the frontswing concludes, as long as there is at
+/// least one target.
+///
+/// Falloff is measured from the entity to the farthest corner of its range.
+#[derive(Clone, Component, Debug, Default)]
+pub struct AreaAttack {
+    pub damage_type: DamageType,
+    /// How damage scales with distance from the entity.
+    pub falloff: Falloff,
+    /// Which entities are caught in the area.
+    pub filter: HitFilter,
+    in_frontswing: bool,
+}
+
+impl AreaAttack {
+    /// Constructs an `AreaAttack` with a [`DamageType`].
+    pub fn with_type(self, damage_type: DamageType) -> AreaAttack {
+        AreaAttack {
+            damage_type,
+            ..self
+        }
+    }
+
+    /// Constructs an `AreaAttack` with a [`Falloff`].
+    pub fn with_falloff(self, falloff: Falloff) -> AreaAttack {
+        AreaAttack {
+            falloff,
+            ..self
+        }
+    }
+
+    /// Constructs an `AreaAttack` with a [`HitFilter`].
+    pub fn with_filter(self, filter: HitFilter) -> AreaAttack {
+        AreaAttack {
+            filter,
+            ..self
+        }
+    }
+}
+
+/// An autoattack scheme that hits each target as soon as the frontswing
+/// concludes, then bounces to the closest entity that hasn't been hit yet.
+///
+/// Each bounce multiplies the damage by [`Chain::decay`].
+#[derive(Clone, Component, Debug)]
+pub struct Chain {
+    /// The maximum amount of times the attack bounces after the first hit.
+    pub bounces: usize,
+    /// How far the attack can bounce from the last entity hit.
+    pub radius: f32,
+    /// The damage multiplier applied on every bounce.
+    pub decay: f32,
+    pub damage_type: DamageType,
+    /// Which entities the attack can bounce to.
+    pub filter: HitFilter,
+    in_frontswing: bool,
+}
+
+impl Chain {
+    /// Creates a new `Chain`.
+    pub fn new(bounces: usize, radius: f32, decay: f32) -> Chain {
+        Chain {
+            bounces,
+            radius,
+            decay,
+            damage_type: DamageType::default(),
+            filter: HitFilter::default(),
+            in_frontswing: false,
+        }
+    }
+
+    /// Constructs a `Chain` with a [`DamageType`].
+    pub fn with_type(self, damage_type: DamageType) -> Chain {
+        Chain {
+            damage_type,
+            ..self
+        }
+    }
+
+    /// Constructs a `Chain` with a [`HitFilter`].
+    pub fn with_filter(self, filter: HitFilter) -> Chain {
+        Chain {
+            filter,
+            ..self
+        }
+    }
+}
+
 // FIXME: this component and related systems are terrible.
 // In reality, this should just be a single timer that is used up when the
 // enemy attacks. The animation system should drive this. This is just a hacky
@@ -234,6 +392,157 @@ pub fn do_melee_auto_attack(
     }
 }
 
+pub fn do_splash_auto_attack(
+    mut query: Query<(Entity, &AttackCycle, &Targets, &mut Splash)>,
+    mut damage_received_tx: EventWriter<DamageReceivedEvent>,
+    parents_query: Query<&Parent>,
+    atk_stats_query: Query<&ComputedStat<stat::Atk>>,
+    hostility_query: Query<&Hostility>,
+    position_query: Query<&GlobalTransform>,
+    area_query: AreaQuery,
+) {
+    for (entity, attack_cycle, targets, mut splash) in query.iter_mut() {
+        // check if we can do an attack
+        if !attack_cycle.in_frontswing() && splash.in_frontswing {
+            let Some(atk) = find_stats(entity, &parents_query, &atk_stats_query) else {
+                continue;
+            };
+
+            let hostility = find_stats(entity, &parents_query, &hostility_query)
+                .copied()
+                .unwrap_or_default();
+
+            // do not hit an entity twice if splashes overlap
+            let mut hit = HashSet::new();
+
+            for target in targets.iter() {
+                let Ok(target_transform) = position_query.get(*target) else {
+                    continue;
+                };
+
+                let center = target_transform.translation().truncate();
+
+                for area_hit in area_query.within_radius(center, splash.radius, hostility, splash.filter) {
+                    if !hit.insert(area_hit.entity) {
+                        continue;
+                    }
+
+                    let scale = splash.falloff.scale(area_hit.distance, splash.radius);
+
+                    damage_received_tx.send(DamageReceivedEvent::new(area_hit.entity)
+                        .with_type(splash.damage_type)
+                        .with_damage(atk.get() as f32 * scale));
+                }
+            }
+        }
+
+        splash.in_frontswing = attack_cycle.in_frontswing();
+    }
+}
+
+pub fn do_area_auto_attack(
+    mut query: Query<(Entity, &GlobalTransform, &AttackCycle, &Targets, &Range, &mut AreaAttack)>,
+    mut damage_received_tx: EventWriter<DamageReceivedEvent>,
+    parents_query: Query<&Parent>,
+    atk_stats_query: Query<&ComputedStat<stat::Atk>>,
+    hostility_query: Query<&Hostility>,
+    area_query: AreaQuery,
+) {
+    for (entity, transform, attack_cycle, targets, range, mut area_attack) in query.iter_mut() {
+        // check if we can do an attack
+        if !attack_cycle.in_frontswing() && area_attack.in_frontswing && !targets.is_empty() {
+            let Some(atk) = find_stats(entity, &parents_query, &atk_stats_query) else {
+                continue;
+            };
+
+            let hostility = find_stats(entity, &parents_query, &hostility_query)
+                .copied()
+                .unwrap_or_default();
+
+            // falloff is measured to the farthest corner
+            let center = transform.translation().truncate();
+            let (min, max) = range.bounds(transform);
+            let radius = [min, max, Vec2::new(min.x, max.y), Vec2::new(max.x, min.y)]
+                .into_iter()
+                .map(|corner| center.distance(corner))
+                .fold(0.0, f32::max);
+
+            for area_hit in area_query.within(range, transform, hostility, area_attack.filter) {
+                let scale = area_attack.falloff.scale(area_hit.distance, radius);
+
+                damage_received_tx.send(DamageReceivedEvent::new(area_hit.entity)
+                    .with_type(area_attack.damage_type)
+                    .with_damage(atk.get() as f32 * scale));
+            }
+        }
+
+        area_attack.in_frontswing = attack_cycle.in_frontswing();
+    }
+}
+
+pub fn do_chain_auto_attack(
+    mut query: Query<(Entity, &AttackCycle, &Targets, &mut Chain)>,
+    mut damage_received_tx: EventWriter<DamageReceivedEvent>,
+    parents_query: Query<&Parent>,
+    atk_stats_query: Query<&ComputedStat<stat::Atk>>,
+    hostility_query: Query<&Hostility>,
+    position_query: Query<&GlobalTransform>,
+    area_query: AreaQuery,
+) {
+    for (entity, attack_cycle, targets, mut chain) in query.iter_mut() {
+        // check if we can do an attack
+        if !attack_cycle.in_frontswing() && chain.in_frontswing {
+            let Some(atk) = find_stats(entity, &parents_query, &atk_stats_query) else {
+                continue;
+            };
+
+            let hostility = find_stats(entity, &parents_query, &hostility_query)
+                .copied()
+                .unwrap_or_default();
+
+            for target in targets.iter() {
+                // each target starts its own chain
+                let mut hit = HashSet::from([*target]);
+                let mut current = *target;
+                let mut damage = atk.get() as f32;
+
+                damage_received_tx.send(DamageReceivedEvent::new(current)
+                    .with_type(chain.damage_type)
+                    .with_damage(damage));
+
+                for _ in 0..chain.bounces {
+                    let Ok(current_transform) = position_query.get(current) else {
+                        break;
+                    };
+
+                    let center = current_transform.translation().truncate();
+
+                    // bounce to the closest entity not hit yet
+                    let next = area_query
+                        .within_radius(center, chain.radius, hostility, chain.filter)
+                        .into_iter()
+                        .filter(|area_hit| !hit.contains(&area_hit.entity))
+                        .min_by(|a, b| a.distance.total_cmp(&b.distance));
+
+                    let Some(next) = next else {
+                        break;
+                    };
+
+                    hit.insert(next.entity);
+                    current = next.entity;
+                    damage *= chain.decay;
+
+                    damage_received_tx.send(DamageReceivedEvent::new(current)
+                        .with_type(chain.damage_type)
+                        .with_damage(damage));
+                }
+            }
+        }
+
+        chain.in_frontswing = attack_cycle.in_frontswing();
+    }
+}
+
 pub fn standby_with_no_targets(
     mut query: Query<(Entity, &mut AttackCycle)>,
     children_query: Query<&Children>,
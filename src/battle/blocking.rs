@@ -3,11 +3,15 @@
 use bevy::prelude::*;
 use bevy::math::swizzles::*;
 
+use std::collections::HashMap;
+
 use crate::tile_map::nav::{Nav, NavSystem};
 use crate::stats::{stat, ComputedStat};
 
-use super::damage::DeathEvent;
+use super::damage::{Dead, DeathEvent};
 use super::auto_attack::AttackCycle;
+use super::displacement::Displaced;
+use super::path::Hidden;
 use super::BoundingCircle;
 
 pub struct BlockingPlugin;
@@ -17,17 +21,16 @@ impl Plugin for BlockingPlugin {
         app
             .add_systems(Update,
                 (
-                    start_blocking
+                    update_blocking
                         .before(NavSystem::Steering),
                     disable_nav_for_blocking
                         .before(NavSystem::Steering)
-                        .after(start_blocking),
+                        .after(update_blocking),
                     disengage_dead_blockers
-                        .before(disable_nav_for_blocking)
-                        .after(start_blocking),
+                        .before(update_blocking),
                     // TODO: do we need this???
                     reset_attack_cycles
-                        .after(start_blocking),
+                        .after(update_blocking),
                 )
             );
     }
@@ -51,21 +54,46 @@ impl Default for Blocker {
     }
 }
 
+/// How far a blockable can drift out of contact with its blocker before the
+/// block is released.
+pub const BLOCK_RELEASE_SLACK: f32 = 0.1;
+
 /// An entity that can be blocked by another entity.
 ///
 /// If the entity has an `AttackCycle` component, changes to this will reset
 /// the `AttackCycle`.
-#[derive(Clone, Component, Debug, Default)]
+#[derive(Clone, Component, Debug)]
 pub struct Blockable {
     pub blocked_by: Option<Entity>,
+    /// How much of a blocker's [`stat::Block`] this entity takes up.
+    ///
+    /// Large enemies can take 2 or 3 block, and can't be blocked by blockers
+    /// with less block than that. This is separate from [`stat::Weight`],
+    /// which only resists forced movement: a heavy enemy isn't necessarily a
+    /// large one.
+    pub cost: i32,
 }
 
 impl Blockable {
+    /// Creates a new `Blockable` with a block cost.
+    pub fn with_cost(cost: i32) -> Blockable {
+        Blockable {
+            blocked_by: None,
+            cost,
+        }
+    }
+
     pub fn is_blocked(&self) -> bool {
         self.blocked_by.is_some()
     }
 }
 
+impl Default for Blockable {
+    fn default() -> Blockable {
+        Blockable::with_cost(1)
+    }
+}
+
 pub fn reset_attack_cycles(
     mut blockable_query: Query<&mut AttackCycle, Changed<Blockable>>,
 ) {
@@ -107,56 +135,141 @@ pub fn disable_nav_for_blocking(
     }
 }
 
-pub fn start_blocking(
-    mut blockable_query: Query<(Entity, &GlobalTransform, &BoundingCircle, &mut Blockable)>,
+/// Releases blocks that are no longer valid, then starts new blocks.
+///
+/// A block is released if:
+/// * The blocker can no longer block, retreated or died.
+/// * The blocker's [`stat::Block`] dropped below the total cost of what it
+///   is blocking. The most recently blocked entities are released first.
+/// * The blockable died, disappeared or was pushed out of contact.
+///
+/// Blockables that are being [`Displaced`] are never blocked.
+///
+/// New blocks are started in entity order, and each blockable is blocked by
+/// the closest blocker in contact with enough block left.
+pub fn update_blocking(
+    mut blockable_query: Query<(Entity, &GlobalTransform, &BoundingCircle, &mut Blockable, Option<&Hidden>, Option<&Dead>)>,
     mut blocker_query: Query<(Entity, &GlobalTransform, &BoundingCircle, &mut Blocker, &ComputedStat<stat::Block>)>,
+    displaced_query: Query<(), With<Displaced>>,
 ) {
+    let mut used_block = HashMap::<Entity, i32>::new();
+
+    // release blocks from the blocker side
     for (
-        blockable_entity,
-        blockable_transform,
-        blockable_bounding_circle,
-        mut blockable,
-    ) in blockable_query.iter_mut() {
-        // no need to start another blocking interaction if this is already
-        // being blocked.
-        if blockable.is_blocked() {
+        blocker_entity,
+        blocker_transform,
+        blocker_bounding_circle,
+        mut blocker,
+        block_stat,
+    ) in blocker_query.iter_mut() {
+        let capacity = if blocker.can_block { block_stat.get() } else { 0 };
+        let blocker_pos = blocker_transform.translation().xy();
+        let mut used = 0;
+
+        blocker.blocking.retain(|entity| {
+            let Ok((_, transform, bounding_circle, blockable, hidden, dead)) = blockable_query.get(*entity) else {
+                return false;
+            };
+
+            if hidden.is_some() || dead.is_some() || blockable.blocked_by != Some(blocker_entity) {
+                return false;
+            }
+
+            // pushed out of contact
+            let max_distance = bounding_circle.radius + blocker_bounding_circle.radius + BLOCK_RELEASE_SLACK;
+
+            if transform.translation().xy().distance(blocker_pos) > max_distance {
+                return false;
+            }
+
+            // over capacity
+            if used + blockable.cost > capacity {
+                return false;
+            }
+
+            used += blockable.cost;
+            true
+        });
+
+        used_block.insert(blocker_entity, used);
+    }
+
+    // release blockables whose blocker let go of them
+    for (entity, _, _, mut blockable, _, _) in blockable_query.iter_mut() {
+        let Some(blocked_by) = blockable.blocked_by else {
             continue;
+        };
+
+        let still_blocked = blocker_query
+            .get(blocked_by)
+            .map(|(_, _, _, blocker, _)| blocker.blocking.contains(&entity))
+            .unwrap_or_default();
+
+        if !still_blocked {
+            blockable.blocked_by = None;
         }
+    }
 
-        // project to 2D XZ plane
-        let pos = blockable_transform.translation().xy();
-
-        for (
-            blocker_entity,
-            blocker_transform,
-            blocker_bounding_circle,
-            mut blocker,
-            block_stat,
-        ) in blocker_query.iter_mut() {
-            if !blocker.can_block {
-                continue;
-            }
+    // start new blocks
+    let mut unblocked = blockable_query
+        .iter()
+        .filter(|(entity, _, _, blockable, hidden, dead)| {
+            !blockable.is_blocked()
+                && hidden.is_none()
+                && dead.is_none()
+                && !displaced_query.contains(*entity)
+        })
+        .map(|(entity, ..)| entity)
+        .collect::<Vec<_>>();
 
-            // project to 2D XZ plane
-            let blocker_pos = blocker_transform.translation().xy();
+    unblocked.sort();
 
-            // compare distances
-            let min_distance = blockable_bounding_circle.radius + blocker_bounding_circle.radius;
+    for blockable_entity in unblocked {
+        let Ok((_, transform, bounding_circle, mut blockable, _, _)) = blockable_query.get_mut(blockable_entity) else {
+            continue;
+        };
+
+        // project to 2D XY plane
+        let pos = transform.translation().xy();
 
-            let distance = pos.distance(blocker_pos);
+        let closest = blocker_query
+            .iter()
+            .filter_map(|(blocker_entity, blocker_transform, blocker_bounding_circle, blocker, block_stat)| {
+                if !blocker.can_block {
+                    return None;
+                }
+
+                // compare distances
+                let min_distance = bounding_circle.radius + blocker_bounding_circle.radius;
+                let distance = pos.distance(blocker_transform.translation().xy());
 
-            if distance <= min_distance {
-                // this blocker is ready to block this blockable!
-                // oh god wtf is this sentence
+                if distance > min_distance {
+                    return None;
+                }
 
                 // make sure the entity can actually block more
-                if (blocker.blocking.len() as i32) < block_stat.get() {
-                    // setup blocking pointers
-                    blocker.blocking.push(blockable_entity);
-                    blockable.blocked_by = Some(blocker_entity);
+                let used = used_block.get(&blocker_entity).copied().unwrap_or_default();
+
+                if used + blockable.cost > block_stat.get() {
+                    return None;
                 }
-            }
-        }
+
+                Some((distance, blocker_entity))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+
+        let Some((_, blocker_entity)) = closest else {
+            continue;
+        };
+
+        let Ok((_, _, _, mut blocker, _)) = blocker_query.get_mut(blocker_entity) else {
+            continue;
+        };
+
+        // setup blocking pointers
+        blocker.blocking.push(blockable_entity);
+        blockable.blocked_by = Some(blocker_entity);
+
+        *used_block.entry(blocker_entity).or_default() += blockable.cost;
     }
 }
-
@@ -2,6 +2,8 @@
 
 use bevy::prelude::*;
 
+use serde::{Deserialize, Serialize};
+
 use std::time::Duration;
 
 use crate::stats::{find_stats, stat, ComputedStat};
@@ -39,7 +41,7 @@ pub enum DamageSystems {
 /// Damage type.
 ///
 /// Determines how final damage will be calculated.
-#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Hash, Serialize)]
 pub enum DamageType {
     #[default]
     Physical,
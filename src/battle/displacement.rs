@@ -0,0 +1,300 @@
+//! Forced movement, like pushes, pulls and shifts.
+//!
+//! Displaced entities are moved along a vector over time, breaking any block
+//! they are in. They cannot be moved into solid tiles, but they can be moved
+//! into holes, which is usually the point.
+
+use bevy::prelude::*;
+
+use crate::stats::{stat, ComputedStat};
+use crate::tile_map::{Coordinates, Grid};
+use crate::tile_map::nav::{Nav, NavSystem};
+
+use super::blocking::{self, Blockable};
+
+/// How fast displaced entities with a [`stat::Mass`] of `1.0` move, in
+/// tiles/second.
+pub const DISPLACEMENT_SPEED: f32 = 6.0;
+
+/// Displacement plugin.
+pub struct DisplacementPlugin;
+
+impl Plugin for DisplacementPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<ShiftEvent>()
+            .add_systems(
+                Update,
+                (start_displacement, apply_displacement)
+                    .chain()
+                    .after(blocking::disable_nav_for_blocking)
+                    .before(NavSystem::Steering),
+            );
+    }
+}
+
+/// The force of a shift.
+///
+/// Stronger forces move entities farther.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Force {
+    #[default]
+    Small,
+    Medium,
+    Large,
+    Massive,
+}
+
+impl Force {
+    /// The strength of the force, compared against [`stat::Weight`].
+    pub fn strength(&self) -> i32 {
+        match self {
+            Force::Small => 1,
+            Force::Medium => 2,
+            Force::Large => 3,
+            Force::Massive => 4,
+        }
+    }
+
+    /// The distance in tiles the force moves an entity of a
+    /// [`stat::Weight`].
+    ///
+    /// A force as strong as the weight moves the entity half a tile, and
+    /// every level of strength over the weight adds another half a tile.
+    /// Forces weaker than the weight do not move the entity at all.
+    pub fn distance_against(&self, weight: i32) -> f32 {
+        (self.strength() - weight + 1).max(0) as f32 * 0.5
+    }
+}
+
+/// Shifts an entity in a direction.
+///
+/// For pushes, the direction should point away from the source. For pulls,
+/// towards it.
+#[derive(Clone, Debug, Event)]
+pub struct ShiftEvent {
+    pub entity: Entity,
+    pub direction: Vec2,
+    pub force: Force,
+}
+
+impl ShiftEvent {
+    /// Creates a new `ShiftEvent`.
+    pub fn new(entity: Entity, direction: Vec2, force: Force) -> ShiftEvent {
+        ShiftEvent {
+            entity,
+            direction: direction.normalize_or_zero(),
+            force,
+        }
+    }
+
+    /// Shorthand for a shift that pushes `entity` at `pos` away from `from`.
+    pub fn push(entity: Entity, pos: Vec2, from: Vec2, force: Force) -> ShiftEvent {
+        ShiftEvent::new(entity, pos - from, force)
+    }
+
+    /// Shorthand for a shift that pulls `entity` at `pos` towards `to`.
+    pub fn pull(entity: Entity, pos: Vec2, to: Vec2, force: Force) -> ShiftEvent {
+        ShiftEvent::new(entity, to - pos, force)
+    }
+}
+
+/// An entity that is currently being displaced.
+///
+/// Displaced entities cannot navigate or be blocked.
+#[derive(Clone, Component, Debug)]
+pub struct Displaced {
+    direction: Vec2,
+    speed: f32,
+    remaining: f32,
+}
+
+impl Displaced {
+    /// How much farther the entity will be moved.
+    pub fn remaining(&self) -> f32 {
+        self.remaining
+    }
+}
+
+pub fn start_displacement(
+    mut commands: Commands,
+    mut query: Query<(
+        Option<&mut Blockable>,
+        Option<&ComputedStat<stat::Weight>>,
+        Option<&ComputedStat<stat::Mass>>,
+    ), Without<Coordinates>>,
+    mut shift_rx: EventReader<ShiftEvent>,
+) {
+    for ev in shift_rx.iter() {
+        // gridlocked entities cannot be moved
+        let Ok((blockable, weight, mass)) = query.get_mut(ev.entity) else {
+            continue;
+        };
+
+        // entities without the stats behave like they have the defaults
+        let weight = weight.map_or_else(|| stat::Weight::default().get(), |w| w.get());
+        let mass = mass.map_or_else(|| stat::Mass::default().get(), |m| m.get());
+
+        let distance = ev.force.distance_against(weight);
+
+        if distance <= 0.0 || ev.direction == Vec2::ZERO {
+            continue;
+        }
+
+        // break blocking, the blocker will let go on its own
+        if let Some(mut blockable) = blockable {
+            if blockable.is_blocked() {
+                blockable.blocked_by = None;
+            }
+        }
+
+        commands
+            .entity(ev.entity)
+            .insert(Displaced {
+                direction: ev.direction,
+                speed: DISPLACEMENT_SPEED / mass,
+                remaining: distance,
+            });
+    }
+}
+
+pub fn apply_displacement(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Transform, &mut Displaced, Option<&mut Nav>, Option<&Blockable>)>,
+    grid_query: Query<(&Grid, &GlobalTransform)>,
+    time: Res<Time>,
+) {
+    let Ok((grid, grid_transform)) = grid_query.get_single() else {
+        return;
+    };
+
+    for (entity, mut transform, mut displaced, nav, blockable) in query.iter_mut() {
+        let step = (displaced.speed * time.delta_seconds()).min(displaced.remaining);
+        let next = transform.translation + (displaced.direction * step).extend(0.0);
+
+        // do not move into solid tiles, or off the grid
+        let local = grid_transform.affine().inverse().transform_point(next);
+        let can_move = grid
+            .get(&Coordinates::nearest(local))
+            .map(|tile| !tile.is_solid())
+            .unwrap_or_default();
+
+        if can_move {
+            transform.translation = next;
+            displaced.remaining -= step;
+        } else {
+            displaced.remaining = 0.0;
+        }
+
+        let finished = displaced.remaining <= 0.0;
+
+        if let Some(mut nav) = nav {
+            if finished {
+                // resume navigation from the new position
+                nav.active = !blockable.map(|b| b.is_blocked()).unwrap_or_default();
+
+                let target = nav.target();
+                nav.set_target(target);
+            } else if nav.active {
+                nav.active = false;
+            }
+        }
+
+        if finished {
+            commands
+                .entity(entity)
+                .remove::<Displaced>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::{Duration, Instant};
+
+    use crate::tile_map::{Tile, TileKind};
+
+    /// Pushes a unit at `(2, 0)` along `direction`, in a corridor walled off
+    /// at `x = 0` and `x = 4`, and returns where it stops.
+    fn push_into_wall(direction: Vec2) -> f32 {
+        let mut app = App::new();
+
+        app
+            .init_resource::<Time>()
+            .add_systems(Update, apply_displacement);
+
+        let tiles = (0..=4).map(|x| {
+            let kind = if x == 0 || x == 4 {
+                TileKind::Wall
+            } else {
+                TileKind::Ground
+            };
+
+            (Coordinates::new(x, 0), Tile::new(kind, false))
+        });
+
+        app.world.spawn((Grid::from_tiles(tiles), GlobalTransform::default()));
+
+        let unit = app.world
+            .spawn((
+                Transform::from_xyz(2.0, 0.0, 0.0),
+                Displaced {
+                    direction,
+                    speed: DISPLACEMENT_SPEED,
+                    remaining: 3.0,
+                },
+            ))
+            .id();
+
+        let start = Instant::now();
+
+        for frame in 0..120 {
+            app.world
+                .resource_mut::<Time>()
+                .update_with_instant(start + Duration::from_secs_f32(frame as f32 / 60.0));
+            app.update();
+        }
+
+        assert!(app.world.get::<Displaced>(unit).is_none());
+
+        app.world.get::<Transform>(unit).unwrap().translation.x
+    }
+
+    #[test]
+    fn stops_at_wall_edge() {
+        let step = DISPLACEMENT_SPEED / 60.0;
+
+        let right = push_into_wall(Vec2::X);
+        assert!(right < 3.5 && right >= 3.5 - step, "stopped at {}", right);
+
+        let left = push_into_wall(Vec2::NEG_X);
+        assert!(left > 0.5 && left <= 0.5 + step, "stopped at {}", left);
+    }
+
+    #[test]
+    fn unweighted_displaced_like_default_weight() {
+        let mut app = App::new();
+
+        app
+            .add_event::<ShiftEvent>()
+            .add_systems(Update, start_displacement);
+
+        let unweighted = app.world.spawn_empty().id();
+        let weighted = app.world
+            .spawn(ComputedStat::<stat::Weight>::default())
+            .id();
+
+        for entity in [unweighted, weighted] {
+            app.world.send_event(ShiftEvent::new(entity, Vec2::X, Force::Medium));
+        }
+
+        app.update();
+
+        let remaining = |entity| app.world.get::<Displaced>(entity).unwrap().remaining();
+
+        assert_eq!(remaining(unweighted), remaining(weighted));
+        assert_eq!(remaining(unweighted), Force::Medium.distance_against(1));
+    }
+}
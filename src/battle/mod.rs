@@ -3,9 +3,11 @@
 //! Only components and systems that are related to the action of battle should
 //! be placed here, **not** UI or player assistance structs, nor data loading.
 
+pub mod area;
 pub mod auto_attack;
 pub mod damage;
 pub mod blocking;
+pub mod displacement;
 pub mod path;
 pub mod skill;
 pub mod targeting;
@@ -35,6 +37,7 @@ impl PluginGroup for BattlePlugins {
             .add(auto_attack::AutoAttackPlugin)
             .add(damage::DamagePlugin)
             .add(blocking::BlockingPlugin)
+            .add(displacement::DisplacementPlugin)
             .add(path::PathPlugin)
             .add(targeting::TargetingPlugin)
             .add(skill::SkillPlugin)
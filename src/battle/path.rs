@@ -8,7 +8,11 @@
 
 use bevy::prelude::*;
 
-use crate::tile_map::nav::{Nav, NavigationFinishEvent};
+use std::time::Duration;
+
+use crate::tile_map::nav::{Nav, CalculatedPath, NavigationFinishEvent, NavSystem};
+
+use super::targeting::{Stealth, Hatred, TargetingSystems};
 
 /// Pathing plugin.
 pub struct PathPlugin;
@@ -17,11 +21,15 @@ impl Plugin for PathPlugin {
     fn build(&self, app: &mut App) {
         app
             .add_event::<CheckpointPassedEvent>()
+            .add_event::<CheckpointSignalEvent>()
             .add_systems(
                 Update,
                 (
-                    start_followers,
-                    update_followers_navigation,
+                    update_followers
+                        .before(NavSystem::Compute),
+                    update_follower_hatred
+                        .after(NavSystem::Steering)
+                        .before(TargetingSystems::SortTargets),
                 ),
             );
             //.add_systems(Update, follow_path)
@@ -34,11 +42,48 @@ impl Plugin for PathPlugin {
 /// If the previous checkpoint has a wait time, this is fired **after**
 /// waiting.
 ///
-/// The [`Follower::target`] will be the next checkpoint the entity must reach.
+/// The [`Follower::next`] will be the next checkpoint the entity must reach.
 /// If it is `None`, the `Follower` is complete.
 #[derive(Clone, Debug, Event)]
 pub struct CheckpointPassedEvent(pub Entity);
 
+/// Releases [`Follower`]s waiting on a [`CheckpointKind::WaitForSignal`]
+/// checkpoint with a matching signal name.
+///
+/// If [`CheckpointSignalEvent::entity`] is `Some`, only that follower is
+/// released.
+#[derive(Clone, Debug, Event)]
+pub struct CheckpointSignalEvent {
+    pub signal: String,
+    pub entity: Option<Entity>,
+}
+
+impl CheckpointSignalEvent {
+    /// Creates a new `CheckpointSignalEvent` that releases every follower
+    /// waiting on `signal`.
+    pub fn new(signal: impl Into<String>) -> CheckpointSignalEvent {
+        CheckpointSignalEvent {
+            signal: signal.into(),
+            entity: None,
+        }
+    }
+
+    /// Constructs a `CheckpointSignalEvent` that only releases one entity.
+    pub fn with_entity(self, entity: Entity) -> CheckpointSignalEvent {
+        CheckpointSignalEvent {
+            entity: Some(entity),
+            ..self
+        }
+    }
+}
+
+/// A marker component for followers that have disappeared through a
+/// [`CheckpointKind::Disappear`] checkpoint.
+///
+/// Hidden entities are invisible, cannot be targeted and cannot be blocked.
+#[derive(Clone, Component, Debug, Default)]
+pub struct Hidden;
+
 /// A `Follower` paths itself between two or more checkpoints on the map.
 ///
 /// This component (and related systems) do not actually do any work finding
@@ -49,6 +94,7 @@ pub struct CheckpointPassedEvent(pub Entity);
 pub struct Follower {
     checkpoints: Vec<Checkpoint>,
     current_idx: usize,
+    state: FollowerState,
 }
 
 impl Follower {
@@ -57,16 +103,14 @@ impl Follower {
         Follower {
             checkpoints: checkpoints.into(),
             current_idx: 0,
+            state: FollowerState::Pending,
         }
     }
 
     /// Shorthand to create a `Follower` that spawns at a single checkpoint and
     /// does not move.
     pub fn start_at(checkpoint: Checkpoint) -> Follower {
-        Follower {
-            checkpoints: vec![checkpoint],
-            current_idx: 0,
-        }
+        Follower::new(vec![checkpoint])
     }
 
     /// Checks if the follower's pathing is complete.
@@ -85,26 +129,91 @@ impl Follower {
         self.checkpoints.get(self.current_idx)
     }
 
+    /// The checkpoints that have not been passed yet, including the current
+    /// checkpoint.
+    pub fn remaining(&self) -> &[Checkpoint] {
+        self.checkpoints.get(self.current_idx..).unwrap_or_default()
+    }
+
+    /// Checks if the follower is currently waiting at a checkpoint, either
+    /// for a set amount of time or for a signal.
+    pub fn is_waiting(&self) -> bool {
+        matches!(self.state, FollowerState::Waiting(_) | FollowerState::Signal)
+    }
+
+    /// The distance the follower still has to travel from `from` to reach its
+    /// last checkpoint.
+    ///
+    /// The leg to the current checkpoint follows the [`CalculatedPath`], but
+    /// every leg after that is approximated as a straight line, since those
+    /// haven't been pathfound yet.
+    pub fn remaining_distance(&self, path: &CalculatedPath, from: Vec3) -> f32 {
+        let mut checkpoints = self.remaining().iter();
+        let mut pos = from;
+        let mut distance = 0.0;
+
+        if let FollowerState::Moving = self.state {
+            if let Some(last) = path.waypoints().last() {
+                distance += path.distance_from(from);
+                pos = last;
+                checkpoints.next();
+            }
+        }
+
+        for checkpoint in checkpoints {
+            match checkpoint.kind {
+                CheckpointKind::Move => {
+                    distance += pos.distance(checkpoint.target());
+                    pos = checkpoint.target();
+                }
+                CheckpointKind::Teleport | CheckpointKind::AppearAt => {
+                    pos = checkpoint.target();
+                }
+                _ => (),
+            }
+        }
+
+        distance
+    }
+
     /// Advances to the next checkpoint, returning the next checkpoint.
     ///
     /// Mostly used internally, but this can be manually called to skip
     /// checkpoints.
     pub fn advance(&mut self) -> Option<&Checkpoint> {
         self.current_idx += 1;
+        self.state = FollowerState::Pending;
         self.next()
     }
 }
 
+/// The progress of a [`Follower`] on its current checkpoint.
+#[derive(Clone, Debug, Default)]
+enum FollowerState {
+    /// The current checkpoint has not been started.
+    #[default]
+    Pending,
+    /// Navigating to the current checkpoint.
+    Moving,
+    /// Waiting for a timer.
+    Waiting(Timer),
+    /// Waiting for a [`CheckpointSignalEvent`].
+    Signal,
+    /// The current checkpoint is complete, and can be advanced on next
+    /// update.
+    Done,
+}
+
 /// A checkpoint is a single destination in a [`Follower`]'s path.
 #[derive(Clone, Debug)]
 pub struct Checkpoint {
     /// The position to reach.
+    ///
+    /// Only [`CheckpointKind::Move`], [`CheckpointKind::Teleport`] and
+    /// [`CheckpointKind::AppearAt`] use this.
     pub pos: Vec2,
-    /*
-    /// How long the [`Follower`] will wait in seconds until moving to the next
-    /// checkpoint.
-    pub wait_time: f32,
-    */
+    /// What the [`Follower`] does at this checkpoint.
+    pub kind: CheckpointKind,
 }
 
 impl Checkpoint {
@@ -112,37 +221,255 @@ impl Checkpoint {
     pub fn at(pos: Vec2) -> Checkpoint {
         Checkpoint {
             pos,
+            kind: CheckpointKind::Move,
+        }
+    }
+
+    /// Shorthand for a checkpoint that waits in place for `duration`.
+    pub fn wait(duration: Duration) -> Checkpoint {
+        Checkpoint {
+            pos: Vec2::ZERO,
+            kind: CheckpointKind::Wait(duration),
+        }
+    }
+
+    /// Shorthand for a checkpoint that waits in place until a
+    /// [`CheckpointSignalEvent`] named `signal` is sent.
+    pub fn wait_for(signal: impl Into<String>) -> Checkpoint {
+        Checkpoint {
+            pos: Vec2::ZERO,
+            kind: CheckpointKind::WaitForSignal(signal.into()),
+        }
+    }
+
+    /// Shorthand for a checkpoint that instantly moves to `pos`.
+    pub fn teleport(pos: Vec2) -> Checkpoint {
+        Checkpoint {
+            pos,
+            kind: CheckpointKind::Teleport,
+        }
+    }
+
+    /// Shorthand for a checkpoint that hides the follower in place.
+    pub fn disappear() -> Checkpoint {
+        Checkpoint {
+            pos: Vec2::ZERO,
+            kind: CheckpointKind::Disappear,
+        }
+    }
+
+    /// Shorthand for a checkpoint that instantly moves to `pos` and reveals
+    /// the follower.
+    pub fn appear_at(pos: Vec2) -> Checkpoint {
+        Checkpoint {
+            pos,
+            kind: CheckpointKind::AppearAt,
         }
     }
+
+    /// The world position of the checkpoint.
+    ///
+    /// Like tiles, checkpoints lie on the XY plane.
+    pub fn target(&self) -> Vec3 {
+        self.pos.extend(0.0)
+    }
+}
+
+/// What a [`Follower`] does when it reaches a [`Checkpoint`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum CheckpointKind {
+    /// Navigates to [`Checkpoint::pos`].
+    #[default]
+    Move,
+    /// Waits in place for a duration.
+    Wait(Duration),
+    /// Waits in place until a [`CheckpointSignalEvent`] with this name is
+    /// sent.
+    WaitForSignal(String),
+    /// Instantly moves to [`Checkpoint::pos`], like a tunnel.
+    Teleport,
+    /// Becomes [`Hidden`] in place.
+    Disappear,
+    /// Instantly moves to [`Checkpoint::pos`] and stops being [`Hidden`].
+    AppearAt,
 }
 
-/// System that starts newly spawned [`Follower`]s.
-fn start_followers(
-    mut query: Query<(&Follower, &mut Nav), Added<Follower>>,
-    //mut check_passed_tx: EventWriter<CheckpointPassedEvent>,
+/// System that moves [`Follower`]s through their checkpoints.
+fn update_followers(
+    mut commands: Commands,
+    mut query: Query<(
+        Entity,
+        &mut Follower,
+        &mut Nav,
+        &mut Transform,
+        &mut Visibility,
+        Option<&mut Stealth>,
+    )>,
+    mut nav_finished_rx: EventReader<NavigationFinishEvent>,
+    mut signal_rx: EventReader<CheckpointSignalEvent>,
+    mut check_passed_tx: EventWriter<CheckpointPassedEvent>,
+    time: Res<Time>,
 ) {
-    for (follower, mut nav) in query.iter_mut() {
-        // set next checkpoint
-        if let Some(next) = follower.next() {
-            let target = Vec3::new(next.pos.x, 0.0, next.pos.y);
-            nav.set_target(target);
+    // complete followers that have reached their checkpoint
+    for ev in nav_finished_rx.iter() {
+        if let Ok((_, mut follower, ..)) = query.get_mut(ev.0) {
+            if let FollowerState::Moving = follower.state {
+                follower.state = FollowerState::Done;
+            }
+        }
+    }
+
+    // release followers waiting on signals
+    for ev in signal_rx.iter() {
+        for (entity, mut follower, ..) in query.iter_mut() {
+            if ev.entity.map(|e| e != entity).unwrap_or_default() {
+                continue;
+            }
+
+            let waiting = match (&follower.state, follower.next().map(|c| &c.kind)) {
+                (FollowerState::Signal, Some(CheckpointKind::WaitForSignal(signal))) => {
+                    *signal == ev.signal
+                }
+                _ => false,
+            };
+
+            if waiting {
+                follower.state = FollowerState::Done;
+            }
+        }
+    }
+
+    for (
+        entity,
+        mut follower,
+        mut nav,
+        mut transform,
+        mut visibility,
+        mut stealth,
+    ) in query.iter_mut() {
+        loop {
+            match &mut follower.state {
+                FollowerState::Pending => {
+                    let Some(next) = follower.next().cloned() else {
+                        break;
+                    };
+
+                    let state = match next.kind {
+                        CheckpointKind::Move => {
+                            nav.set_target(next.target());
+                            FollowerState::Moving
+                        }
+                        CheckpointKind::Wait(duration) => {
+                            FollowerState::Waiting(Timer::new(duration, TimerMode::Once))
+                        }
+                        CheckpointKind::WaitForSignal(_) => FollowerState::Signal,
+                        CheckpointKind::Teleport | CheckpointKind::AppearAt => {
+                            transform.translation = next.target();
+
+                            if next.kind == CheckpointKind::AppearAt {
+                                *visibility = Visibility::Inherited;
+
+                                if let Some(stealth) = stealth.as_mut() {
+                                    stealth.visible = true;
+                                }
+
+                                commands.entity(entity).remove::<Hidden>();
+                            }
+
+                            // the global transform is stale until it is
+                            // propagated, so let navigation pick the new
+                            // position up next frame
+                            follower.state = FollowerState::Done;
+                            break;
+                        }
+                        CheckpointKind::Disappear => {
+                            *visibility = Visibility::Hidden;
+
+                            if let Some(stealth) = stealth.as_mut() {
+                                stealth.visible = false;
+                            }
+
+                            commands.entity(entity).insert(Hidden);
+
+                            FollowerState::Done
+                        }
+                    };
+
+                    follower.state = state;
+                }
+                FollowerState::Moving | FollowerState::Signal => break,
+                FollowerState::Waiting(timer) => {
+                    timer.tick(time.delta());
+
+                    if !timer.finished() {
+                        break;
+                    }
+
+                    follower.state = FollowerState::Done;
+                }
+                FollowerState::Done => {
+                    let finished = follower.advance().is_none();
+
+                    check_passed_tx.send(CheckpointPassedEvent(entity));
+
+                    if finished {
+                        break;
+                    }
+                }
+            }
         }
     }
-    
 }
 
-fn update_followers_navigation(
-    mut query: Query<(&mut Follower, &mut Nav)>,
-    mut nav_finished_events: EventReader<NavigationFinishEvent>,
+/// System that updates the [`Hatred`] of [`Follower`]s with how far they are
+/// from the end of their pathing.
+fn update_follower_hatred(
+    mut query: Query<(&Transform, &Follower, &CalculatedPath, &mut Hatred)>,
 ) {
-    for ev in nav_finished_events.iter() {
-        if let Ok((mut follower, mut nav)) = query.get_mut(ev.0) {
-            // set next checkpoint
-            if let Some(next) = follower.advance() {
-                let target = Vec3::new(next.pos.x, 0.0, next.pos.y);
-                nav.set_target(target);
-            }
+    for (transform, follower, path, mut hatred) in query.iter_mut() {
+        let distance = follower.remaining_distance(path, transform.translation);
+        let new_hatred = Hatred((distance * -1000.0) as i32);
+
+        if *hatred != new_hatred {
+            *hatred = new_hatred;
         }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tile_map::Coordinates;
+
+    #[test]
+    fn teleport_lands_on_checkpoint_tile() {
+        let mut app = App::new();
+
+        app
+            .init_resource::<Time>()
+            .add_event::<NavigationFinishEvent>()
+            .add_event::<CheckpointSignalEvent>()
+            .add_event::<CheckpointPassedEvent>()
+            .add_systems(Update, update_followers);
+
+        let entity = app.world
+            .spawn((
+                Follower::new([
+                    Checkpoint::teleport(Vec2::new(3.0, 2.0)),
+                    Checkpoint::wait_for("never"),
+                ]),
+                Nav::default(),
+                Transform::default(),
+                Visibility::default(),
+            ))
+            .id();
+
+        app.update();
+
+        let translation = app.world.get::<Transform>(entity).unwrap().translation;
+
+        assert_eq!(Coordinates::from_local(translation), Coordinates::new(3, 2));
+        assert_eq!(translation.z, 0.0);
+    }
+}
@@ -117,6 +117,20 @@ impl Skill {
         self.sp / self.max_sp
     }
 
+    /// How SP overflowing max SP is handled.
+    pub fn overflow(&self) -> &OverflowBehavior {
+        &self.overflow
+    }
+
+    /// The number of charges that have been filled.
+    ///
+    /// Skills that don't [`Charge`][1] have at most one charge.
+    ///
+    /// [1]: OverflowBehavior::Charge
+    pub fn charges(&self) -> u32 {
+        self.percentage().floor() as u32
+    }
+
     /// Whether the skill is in SP lockout (cannot gain or lose SP).
     pub fn sp_lockout(&self) -> bool {
         self.sp_lockout
@@ -193,6 +207,17 @@ impl SkillLockoutTimer {
     pub fn set(&mut self, duration: Duration) {
         self.0 = Timer::new(duration, TimerMode::Once);
     }
+
+    /// How far along the lockout is, from `0.0` to `1.0`.
+    ///
+    /// This is `1.0` if the skill isn't in lockout.
+    pub fn progress(&self) -> f32 {
+        if self.0.finished() {
+            1.0
+        } else {
+            self.0.percent()
+        }
+    }
 }
 
 /// The duration of a skill.
@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+use bevy::math::swizzles::*;
+
+use super::super::Hostility;
+
+/// Camouflage.
+///
+/// Camouflaged entities cannot be found by the range-based targeting of
+/// ranged units, but melee units see them as usual. They can still be
+/// targeted by whoever is blocking them, or by anyone once a [`TrueSight`]
+/// source has revealed them.
+#[derive(Clone, Component, Debug, Default)]
+pub struct Camouflage {
+    revealed: bool,
+}
+
+impl Camouflage {
+    /// Whether the entity is currently revealed by a [`TrueSight`] source.
+    pub fn is_revealed(&self) -> bool {
+        self.revealed
+    }
+}
+
+/// True sight.
+///
+/// Reveals [`Camouflage`]d entities that this entity is hostile to, within a
+/// radius.
+///
+/// [`Stealth`][1] is left alone on purpose. It also hides followers that
+/// have [`Disappear`][2]ed off the map, which should never be revealed.
+///
+/// [1]: super::Stealth
+/// [2]: crate::battle::path::CheckpointKind::Disappear
+#[derive(Clone, Component, Debug)]
+pub struct TrueSight {
+    pub radius: f32,
+}
+
+impl TrueSight {
+    /// Creates a new `TrueSight` with a radius.
+    pub fn new(radius: f32) -> TrueSight {
+        TrueSight { radius }
+    }
+}
+
+pub fn reveal_camouflage(
+    mut query: Query<(&GlobalTransform, &mut Camouflage, Option<&Hostility>)>,
+    true_sight_query: Query<(&GlobalTransform, &TrueSight, Option<&Hostility>)>,
+) {
+    for (transform, mut camouflage, hostility) in query.iter_mut() {
+        let hostility = hostility.copied().unwrap_or_default();
+        let pos = transform.translation().xy();
+
+        let revealed = true_sight_query
+            .iter()
+            .any(|(source_transform, true_sight, source_hostility)| {
+                let source_hostility = source_hostility.copied().unwrap_or_default();
+
+                source_hostility.is_hostile_to(&hostility)
+                    && source_transform.translation().xy().distance(pos) <= true_sight.radius
+            });
+
+        if camouflage.revealed != revealed {
+            camouflage.revealed = revealed;
+        }
+    }
+}
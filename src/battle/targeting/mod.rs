@@ -4,19 +4,31 @@
 //! determine whether the entity is in range) and a [`Hatred`] component (to
 //! sort multiple targets).
 
+mod camouflage;
 mod priority;
+mod spatial;
 
-pub use priority::{TargetingTree, Hatred};
+pub use camouflage::{Camouflage, TrueSight};
+pub use priority::{TargetingTree, Hatred, Taunt, TargetPriority, TargetCandidate};
+pub use spatial::SpatialIndex;
 
 use std::fmt::{self, Formatter, Debug};
 use std::ops::Deref;
 
 use bevy::prelude::*;
+use bevy::math::swizzles::*;
 
 use parry2d::shape::{Ball, TriMesh};
 
 use super::{BoundingCircle, Hostility};
 use super::blocking::{Blocker, Blockable};
+use super::damage::Health;
+
+use crate::find_parent;
+use crate::stats::{stat, ComputedStat};
+
+use crate::tile_map::{Coordinates, DeployClass};
+use crate::tile_map::range::Range as GridRange;
 
 /// Targeting plugin.
 pub struct TargetingPlugin;
@@ -25,9 +37,15 @@ impl Plugin for TargetingPlugin {
     fn build(&self, app: &mut App) {
         app
             .init_resource::<TargetingTree>()
+            .init_resource::<SpatialIndex>()
             .add_systems(Update,
                 (
-                    priority::sort_targets
+                    (
+                    (sync_grid_ranges, camouflage::reveal_camouflage),
+                    priority::sort_targets,
+                    spatial::build_spatial_index,
+                )
+                        .chain()
                         .in_set(TargetingSystems::SortTargets),
                     (clear_targets, priority_blocked_targets, priority_blocker_target, search_targets)
                         .chain()
@@ -62,6 +80,8 @@ pub struct Range {
 
 #[derive(Clone)]
 enum Shape {
+    /// Covers nothing.
+    None,
     Polygon(TriMesh),
     Circle(Ball),
 }
@@ -81,6 +101,106 @@ impl Range {
     }
 }
 
+impl Range {
+    /// Creates a new circular `Range` centered on the entity.
+    pub fn circle(radius: f32) -> Range {
+        Range {
+            shape: Shape::Circle(Ball { radius }),
+        }
+    }
+
+    /// Creates a new `Range` covering a set of tiles, relative to the entity.
+    ///
+    /// Each tile is a unit square centered on its coordinates. A `Range`
+    /// without any tiles is never in range of anything.
+    pub fn from_tiles<'a>(tiles: impl IntoIterator<Item = &'a Coordinates>) -> Range {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+
+        for tile in tiles {
+            let center = tile.as_vec2();
+            let idx = vertices.len() as u32;
+
+            vertices.extend([
+                center + Vec2::new(-0.5, -0.5),
+                center + Vec2::new(0.5, -0.5),
+                center + Vec2::new(0.5, 0.5),
+                center + Vec2::new(-0.5, 0.5),
+            ].map(|v| v.into()));
+
+            indices.push([idx, idx + 1, idx + 2]);
+            indices.push([idx, idx + 2, idx + 3]);
+        }
+
+        // parry does not allow empty meshes
+        if indices.is_empty() {
+            return Range {
+                shape: Shape::None,
+            };
+        }
+
+        Range {
+            shape: Shape::Polygon(TriMesh::new(vertices, indices)),
+        }
+    }
+}
+
+impl Range {
+    /// The axis-aligned bounds of the range, placed according to `transform`.
+    pub fn bounds(&self, transform: &GlobalTransform) -> (Vec2, Vec2) {
+        let isometry = global_transform_to_isometry(transform);
+
+        let aabb = match &self.shape {
+            Shape::None => {
+                let pos = transform.translation().xy();
+                return (pos, pos);
+            }
+            Shape::Polygon(mesh) => mesh.aabb(&isometry),
+            Shape::Circle(ball) => ball.aabb(&isometry),
+        };
+
+        (aabb.mins.into(), aabb.maxs.into())
+    }
+
+    /// Checks if a [`BoundingCircle`] is in range.
+    ///
+    /// The range is placed and rotated according to `transform`.
+    pub fn intersects(
+        &self,
+        transform: &GlobalTransform,
+        target_transform: &GlobalTransform,
+        target_bounding_circle: &BoundingCircle,
+    ) -> bool {
+        match &self.shape {
+            Shape::None => false,
+            Shape::Polygon(mesh) => {
+                parry2d::query::intersection_test(
+                    &global_transform_to_isometry(transform),
+                    mesh,
+                    &global_transform_to_isometry(target_transform),
+                    &target_bounding_circle.0,
+                )
+                    .unwrap()
+            }
+            Shape::Circle(ball) => {
+                parry2d::query::intersection_test(
+                    &global_transform_to_isometry(transform),
+                    ball,
+                    &global_transform_to_isometry(target_transform),
+                    &target_bounding_circle.0,
+                )
+                    .unwrap()
+            }
+        }
+    }
+}
+
+impl From<&GridRange> for Range {
+    fn from(range: &GridRange) -> Range {
+        Range::from_tiles(range.tiles())
+    }
+}
+
 impl Debug for Range {
     fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
         f.write_str("Range(_)")
@@ -92,29 +212,57 @@ impl Debug for Range {
 pub struct Targeting {
     /// The maximum amount of targets this entity can have.
     pub max_targets: usize,
+    /// How targets found in range are prioritized.
+    pub priority: TargetPriority,
+    /// Whether targets from the last frame are kept over other targets, as
+    /// long as they are still in range.
+    pub sticky: bool,
 }
 
 impl Default for Targeting {
     fn default() -> Targeting {
         Targeting {
             max_targets: 1,
+            priority: TargetPriority::default(),
+            sticky: false,
         }
     }
 }
 
 /// The actual targeting being stored.
 #[derive(Clone, Component, Debug, Default)]
-pub struct Targets(Vec<Entity>);
+pub struct Targets {
+    targets: Vec<Entity>,
+    last: Vec<Entity>,
+}
+
+impl Targets {
+    /// The targets found on the last frame.
+    pub fn last(&self) -> &[Entity] {
+        &self.last
+    }
+}
 
 impl Deref for Targets {
     type Target = [Entity];
 
     fn deref(&self) -> &[Entity] {
-        &self.0
+        &self.targets
     }
 }
 
+/// Marker component for flying units.
+#[derive(Clone, Component, Debug, Default)]
+pub struct Aerial;
+
 /// Component for excluding entities from targeting rules.
+///
+/// Unlike [`Camouflage`], an entity that isn't visible cannot be targeted by
+/// anything, including the entity blocking it. [`TrueSight`] does not reveal
+/// stealth, since this is also how [`Hidden`][1] followers are kept out of
+/// targeting.
+///
+/// [1]: super::path::Hidden
 #[derive(Clone, Component, Debug)]
 pub struct Stealth {
     pub visible: bool,
@@ -128,25 +276,41 @@ impl Default for Stealth {
     }
 }
 
+/// Builds the [`Range`] of entities with a tile-based [`GridRange`] whenever
+/// the tiles change, such as when the range is turned.
+pub fn sync_grid_ranges(
+    mut commands: Commands,
+    query: Query<(Entity, &GridRange), Changed<GridRange>>,
+) {
+    for (entity, range) in query.iter() {
+        commands
+            .entity(entity)
+            .insert(Range::from(range));
+    }
+}
+
 pub fn clear_targets(
     mut query: Query<&mut Targets>,
 ) {
     for mut targets in query.iter_mut() {
-        targets.0.clear();
+        let Targets { targets, last } = &mut *targets;
+
+        std::mem::swap(targets, last);
+        targets.clear();
     }
 }
 
 pub fn priority_blocked_targets(
     mut query: Query<(&Targeting, &mut Targets, &Blocker, Option<&Hostility>)>,
-    targets_query: Query<(Entity, Option<&Hostility>)>,
+    targets_query: Query<(Option<&Hostility>, Option<&Stealth>)>,
 ) {
     for (targeting, mut found_targets, blocker, hostility) in query.iter_mut() {
         let hostility = hostility.copied().unwrap_or_default();
         
         // add all blocked targets to the list
-        let can_take = targeting.max_targets - found_targets.0.len();
+        let can_take = targeting.max_targets - found_targets.targets.len();
 
-        found_targets.0.extend(blocker
+        found_targets.targets.extend(blocker
             .blocking
             .iter()
             .copied()
@@ -154,11 +318,12 @@ pub fn priority_blocked_targets(
                 // check if this entity even still exists
                 // this should be the blocking systems problem but we can do
                 // this at no cost.
-                let Ok((_exists, other_hostility)) = targets_query.get(*e) else {
+                let Ok((other_hostility, stealth)) = targets_query.get(*e) else {
                     return false;
                 };
 
-                hostility.is_hostile_to(&other_hostility.copied().unwrap_or_default())
+                stealth.map(|s| s.visible).unwrap_or(true)
+                    && hostility.is_hostile_to(&other_hostility.copied().unwrap_or_default())
             })
             .take(can_take));
     }
@@ -167,11 +332,11 @@ pub fn priority_blocked_targets(
 // this system means an enemy with no range can actually attack
 pub fn priority_blocker_target(
     mut query: Query<(&Targeting, &mut Targets, &Blockable, Option<&Hostility>)>,
-    targets_query: Query<(Entity, Option<&Hostility>)>,
+    targets_query: Query<(Option<&Hostility>, Option<&Stealth>)>,
 ) {
     for (targeting, mut found_targets, blockable, hostility) in query.iter_mut() {
         // skip if we cannot add any more targets
-        if found_targets.0.len() >= targeting.max_targets {
+        if found_targets.targets.len() >= targeting.max_targets {
             continue;
         }
 
@@ -181,23 +346,39 @@ pub fn priority_blocker_target(
             // check if this entity even still exists
             // this should be the blocking systems problem but we can do
             // this at no cost.
-            let Ok((_exists, other_hostility)) = targets_query.get(blocked_by) else {
+            let Ok((other_hostility, stealth)) = targets_query.get(blocked_by) else {
                 continue;
             };
 
-            if hostility.is_hostile_to(&other_hostility.copied().unwrap_or_default()) {
-                found_targets.0.push(blocked_by);
+            let visible = stealth.map(|s| s.visible).unwrap_or(true);
+
+            if visible && hostility.is_hostile_to(&other_hostility.copied().unwrap_or_default()) {
+                found_targets.targets.push(blocked_by);
             }
         }
     }
 }
 
 pub fn search_targets(
-    mut targeting_query: Query<(&GlobalTransform, &Targeting, &mut Targets, &Range, Option<&Hostility>)>,
-    targets_query: Query<(Entity, &GlobalTransform, &BoundingCircle, Option<&Hostility>, Option<&Stealth>)>,
-    targets_tree: Res<TargetingTree>,
+    mut targeting_query: Query<(Entity, &GlobalTransform, &Targeting, &mut Targets, &Range, Option<&Hostility>)>,
+    targets_query: Query<(
+        Entity,
+        &GlobalTransform,
+        &BoundingCircle,
+        Option<&Hostility>,
+        Option<&Stealth>,
+        Option<&Health>,
+        Option<&ComputedStat<stat::Def>>,
+        Option<&Aerial>,
+        Option<&Taunt>,
+        Option<&Camouflage>,
+    )>,
+    spatial_index: Res<SpatialIndex>,
+    parents_query: Query<&Parent>,
+    class_query: Query<&DeployClass>,
 ) {
     for (
+        entity,
         transform,
         targeting,
         mut found_targets,
@@ -206,58 +387,79 @@ pub fn search_targets(
     ) in targeting_query.iter_mut() {
         let hostility = hostility.copied().unwrap_or_default();
 
-        // find suitable targets
-        let possible_targets = targets_tree
-            .iter()
+        // camouflage only hides from ranged units. skills use the class of
+        // their operator
+        let melee = matches!(
+            find_parent(entity, &parents_query, &class_query),
+            Some(DeployClass::Melee),
+        );
+
+        // find suitable targets near the range
+        let (min, max) = range.bounds(transform);
+
+        let mut candidates = spatial_index
+            .query(min, max)
+            .into_iter()
             .filter_map(|entity| targets_query.get(entity).ok())
+            // filter targets that were already found
+            .filter(|(entity, ..)| !found_targets.contains(entity))
             // filter invisible targets
-            .filter(|(_, _, _, _, stealth)| {
-                stealth.map(|s| s.visible).unwrap_or_else(|| true)
+            .filter(|(_, _, _, _, stealth, ..)| {
+                stealth.map(|s| s.visible).unwrap_or(true)
+            })
+            // filter camouflaged targets that haven't been revealed
+            .filter(|(.., camouflage)| {
+                melee || camouflage.map(|c| c.is_revealed()).unwrap_or(true)
             })
             // filter targets that we aren't hostile to
-            .filter(|(_, _, _, target_hostility, _)| {
+            .filter(|(_, _, _, target_hostility, ..)| {
                 hostility.is_hostile_to(&target_hostility.copied().into())
             })
             // filter shapes we intersect with
-            .filter(|(_, target_transform, target_bounding_circle, _, _)| {
-                match &range.shape {
-                    Shape::Polygon(mesh) => {
-                        parry2d::query::intersection_test(
-                            &global_transform_to_isometry(transform),
-                            mesh,
-                            &global_transform_to_isometry(target_transform),
-                            &target_bounding_circle.0,
-                        )
-                            .unwrap()
-                    }
-                    Shape::Circle(ball) => {
-                        parry2d::query::intersection_test(
-                            &global_transform_to_isometry(transform),
-                            ball,
-                            &global_transform_to_isometry(target_transform),
-                            &target_bounding_circle.0,
-                        )
-                            .unwrap()
-                    }
+            .filter(|(_, target_transform, target_bounding_circle, ..)| {
+                range.intersects(transform, target_transform, target_bounding_circle)
+            })
+            .map(|(entity, target_transform, _, _, _, health, def, aerial, taunt, _)| {
+                TargetCandidate {
+                    entity,
+                    hp: health.map(|h| h.get()),
+                    def: def.map(|d| d.get()),
+                    aerial: aerial.is_some(),
+                    taunt: taunt.copied().unwrap_or_default(),
+                    distance: transform
+                        .translation()
+                        .xy()
+                        .distance(target_transform.translation().xy()),
                 }
-            });
+            })
+            .collect::<Vec<_>>();
+
+        // keep last targets in front, unless something is taunting
+        let sticky: &[Entity] = if targeting.sticky {
+            found_targets.last()
+        } else {
+            &[]
+        };
 
-        let targets = possible_targets
-            .map(|(e, _, _, _, _)| e)
-            .take(targeting.max_targets - found_targets.len());
+        targeting.priority.sort(&mut candidates, sticky);
 
-        found_targets.0.extend(targets);
+        let can_take = targeting.max_targets.saturating_sub(found_targets.len());
+        let targets = candidates
+            .into_iter()
+            .map(|c| c.entity)
+            .take(can_take);
+
+        found_targets.targets.extend(targets);
     }
 }
 
 fn global_transform_to_isometry(t: &GlobalTransform) -> parry2d::math::Isometry<f32> {
-    // TODO: rotation support? oh god
-    //let (rot, _, _) = t.rotation().to_euler(EulerRot::YXZ);
+    let (_, rotation, translation) = t.to_scale_rotation_translation();
 
-    parry2d::math::Isometry {
-        rotation: default(),
-        translation: Vec2::new(t.translation().x, t.translation().y).into(),
-    }
+    // only rotation around the Z axis matters on the XY plane
+    let (angle, _, _) = rotation.to_euler(EulerRot::ZYX);
+
+    parry2d::math::Isometry::new(translation.xy().into(), angle)
 }
 
 pub fn debug_draw_targeting(
@@ -295,6 +497,7 @@ pub fn debug_draw_range(
         };
 
         match &range.shape {
+            Shape::None => (),
             Shape::Polygon(mesh) => {
                 // draw perimeter of mesh
                 for triangle in mesh.triangles() {
@@ -323,3 +526,86 @@ pub fn debug_draw_range(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tile_map::range::Direction;
+
+    const DIRECTIONS: [Direction; 4] = [
+        Direction::Right,
+        Direction::Up,
+        Direction::Left,
+        Direction::Down,
+    ];
+
+    fn tiles() -> [Coordinates; 2] {
+        [Coordinates::new(0, 0), Coordinates::new(1, 0)]
+    }
+
+    fn target_at(pos: Vec2) -> GlobalTransform {
+        GlobalTransform::from_translation(pos.extend(0.0))
+    }
+
+    fn ahead(direction: Direction) -> Vec2 {
+        Vec2::new(direction.cos() as f32, direction.sin() as f32)
+    }
+
+    #[test]
+    fn empty_range_covers_nothing() {
+        let range = Range::from_tiles(&[] as &[Coordinates]);
+        let transform = GlobalTransform::default();
+
+        assert!(!range.intersects(&transform, &target_at(Vec2::ZERO), &BoundingCircle::new(0.5)));
+        assert_eq!(range.bounds(&transform), (Vec2::ZERO, Vec2::ZERO));
+
+        // grid ranges default to no tiles
+        let range = Range::from(&GridRange::default());
+        assert!(!range.intersects(&transform, &target_at(Vec2::ZERO), &BoundingCircle::new(0.5)));
+    }
+
+    #[test]
+    fn rotated_transform_faces_direction() {
+        let range = Range::from_tiles(&tiles());
+        let bounding_circle = BoundingCircle::new(0.15);
+
+        for direction in DIRECTIONS {
+            let transform = GlobalTransform::from(
+                Transform::from_rotation(Quat::from_rotation_z(direction.to_radians())),
+            );
+
+            assert!(
+                range.intersects(&transform, &target_at(ahead(direction)), &bounding_circle),
+                "target ahead should be in range facing {:?}", direction,
+            );
+            assert!(
+                !range.intersects(&transform, &target_at(-ahead(direction)), &bounding_circle),
+                "target behind should not be in range facing {:?}", direction,
+            );
+        }
+    }
+
+    #[test]
+    fn turned_grid_range_faces_direction() {
+        let bounding_circle = BoundingCircle::new(0.15);
+
+        for direction in DIRECTIONS {
+            let mut grid_range = GridRange::new(tiles());
+            grid_range.face_to(direction);
+
+            let range = Range::from(&grid_range);
+            let transform = GlobalTransform::from_translation(Vec3::new(3.0, 2.0, 0.0));
+            let origin = transform.translation().xy();
+
+            assert!(
+                range.intersects(&transform, &target_at(origin + ahead(direction)), &bounding_circle),
+                "target ahead should be in range facing {:?}", direction,
+            );
+            assert!(
+                !range.intersects(&transform, &target_at(origin - ahead(direction)), &bounding_circle),
+                "target behind should not be in range facing {:?}", direction,
+            );
+        }
+    }
+}
@@ -1,7 +1,11 @@
 use bevy::prelude::*;
 
+use serde::Deserialize;
+
 use std::collections::BTreeSet;
+use std::collections::hash_map::RandomState;
 use std::cmp::Ordering;
+use std::hash::{BuildHasher, Hash, Hasher};
 
 /// Hatred.
 ///
@@ -22,9 +26,120 @@ use std::cmp::Ordering;
 #[repr(transparent)]
 pub struct Hatred(pub i32);
 
+/// How a [`Targeting`][1] entity picks between valid targets.
+///
+/// Targets that tie are always ordered by [`Hatred`], and [`Taunt`] always
+/// overrides the priority.
+///
+/// [1]: super::Targeting
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub enum TargetPriority {
+    /// Targets with the highest [`Hatred`] first.
+    #[default]
+    Hatred,
+    /// Targets with the lowest current HP first.
+    LowestHp,
+    /// Targets with the highest DEF first.
+    HighestDef,
+    /// [`Aerial`][1] targets first.
+    ///
+    /// [1]: super::Aerial
+    AerialFirst,
+    /// Targets closest to the targeting entity first.
+    ClosestToSelf,
+    /// Targets in a random order, reshuffled every frame.
+    Random,
+    /// Targets ordered by a custom comparison, where [`Ordering::Less`] means
+    /// the first target is prioritized.
+    ///
+    /// This cannot be loaded from disk.
+    #[serde(skip)]
+    Custom(fn(&TargetCandidate, &TargetCandidate) -> Ordering),
+}
+
+impl TargetPriority {
+    /// Sorts candidates so the most prioritized are first.
+    ///
+    /// The sort is stable, so candidates should already be in [`Hatred`]
+    /// order. Candidates in `sticky` are kept in front of the priority, and
+    /// [`Taunt`] is applied on top of both.
+    pub fn sort(&self, candidates: &mut [TargetCandidate], sticky: &[Entity]) {
+        self.sort_by_priority(candidates);
+
+        candidates.sort_by_key(|c| !sticky.contains(&c.entity));
+        candidates.sort_by_key(|c| std::cmp::Reverse(c.taunt));
+    }
+
+    fn sort_by_priority(&self, candidates: &mut [TargetCandidate]) {
+        match self {
+            TargetPriority::Hatred => (),
+            TargetPriority::LowestHp => {
+                candidates.sort_by(|a, b| {
+                    let a = a.hp.unwrap_or(f32::INFINITY);
+                    let b = b.hp.unwrap_or(f32::INFINITY);
+
+                    a.total_cmp(&b)
+                });
+            }
+            TargetPriority::HighestDef => {
+                candidates.sort_by_key(|c| std::cmp::Reverse(c.def));
+            }
+            TargetPriority::AerialFirst => {
+                candidates.sort_by_key(|c| !c.aerial);
+            }
+            TargetPriority::ClosestToSelf => {
+                candidates.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+            }
+            TargetPriority::Random => {
+                let state = RandomState::new();
+
+                candidates.sort_by_cached_key(|c| {
+                    let mut hasher = state.build_hasher();
+                    c.entity.hash(&mut hasher);
+                    hasher.finish()
+                });
+            }
+            TargetPriority::Custom(f) => candidates.sort_by(*f),
+        }
+    }
+}
+
+/// A valid target found by a [`Targeting`][1] entity, with the details needed
+/// to prioritize it.
+///
+/// [1]: super::Targeting
+#[derive(Clone, Debug)]
+pub struct TargetCandidate {
+    /// The target.
+    pub entity: Entity,
+    /// The current HP of the target, if it has any.
+    pub hp: Option<f32>,
+    /// The DEF of the target, if it has any.
+    pub def: Option<i32>,
+    /// Whether the target is [`Aerial`][1].
+    ///
+    /// [1]: super::Aerial
+    pub aerial: bool,
+    /// The distance from the targeting entity to the target.
+    pub distance: f32,
+    /// The taunt level of the target.
+    pub taunt: Taunt,
+}
+
+/// Taunt.
+///
+/// Entities with a higher taunt level are always targeted before entities with
+/// a lower taunt level, regardless of [`Hatred`] or [`TargetPriority`].
+/// Entities without this component have a taunt level of `0`, and negative
+/// levels make an entity less likely to be targeted.
+#[derive(Clone, Copy, Component, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct Taunt(pub i32);
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 struct SortedEntity {
     entity: Entity,
+    taunt: Taunt,
     hatred: Hatred,
 }
 
@@ -36,14 +151,11 @@ impl PartialOrd for SortedEntity {
 
 impl Ord for SortedEntity {
     fn cmp(&self, other: &SortedEntity) -> Ordering {
-        // first sort by hatred
-        let order = self.hatred.cmp(&other.hatred).reverse();
-
-        match order {
+        // first sort by taunt, then by hatred
+        self.taunt.cmp(&other.taunt).reverse()
+            .then_with(|| self.hatred.cmp(&other.hatred).reverse())
             // order by entity
-            Ordering::Equal => self.entity.cmp(&other.entity).reverse(),
-            order => order,
-        }
+            .then_with(|| self.entity.cmp(&other.entity).reverse())
     }
 }
 
@@ -61,15 +173,51 @@ impl TargetingTree {
 }
 
 pub fn sort_targets(
-    query: Query<(Entity, &Hatred)>,
+    query: Query<(Entity, &Hatred, Option<&Taunt>)>,
     mut hatred_tree: ResMut<TargetingTree>,
 ) {
     hatred_tree.tree.clear();
 
-    for (entity, hatred) in query.iter() {
+    for (entity, hatred, taunt) in query.iter() {
         hatred_tree.tree.insert(SortedEntity {
             entity,
+            taunt: taunt.copied().unwrap_or_default(),
             hatred: *hatred,
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(entity: Entity, taunt: i32) -> TargetCandidate {
+        TargetCandidate {
+            entity,
+            hp: None,
+            def: None,
+            aerial: false,
+            distance: 0.0,
+            taunt: Taunt(taunt),
+        }
+    }
+
+    #[test]
+    fn taunt_overrides_sticky_target() {
+        let held = Entity::from_raw(0);
+        let other = Entity::from_raw(1);
+        let taunt = Entity::from_raw(2);
+
+        let mut candidates = vec![
+            candidate(other, 0),
+            candidate(held, 0),
+            candidate(taunt, 1),
+        ];
+
+        TargetPriority::Hatred.sort(&mut candidates, &[held]);
+
+        let order = candidates.iter().map(|c| c.entity).collect::<Vec<_>>();
+
+        assert_eq!(order, [taunt, held, other]);
+    }
+}
@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+use bevy::math::swizzles::*;
+
+use std::collections::HashMap;
+
+use super::TargetingTree;
+use super::super::BoundingCircle;
+
+/// A uniform grid of every targetable entity.
+///
+/// This is rebuilt once a frame from the [`TargetingTree`], so range queries
+/// only have to look at the cells around the range instead of at every
+/// target. Entities returned from a query keep their [`TargetingTree`]
+/// ordering.
+#[derive(Clone, Debug, Default, Resource)]
+pub struct SpatialIndex {
+    cells: HashMap<IVec2, Vec<IndexedEntity>>,
+    max_radius: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct IndexedEntity {
+    rank: usize,
+    entity: Entity,
+}
+
+impl SpatialIndex {
+    /// The width and height of a single cell, in world units.
+    pub const CELL_SIZE: f32 = 1.0;
+
+    /// Finds all entities whose bounding circles may overlap an axis-aligned
+    /// box, in [`TargetingTree`] order.
+    ///
+    /// This is only a broad-phase check, so the results must still be tested
+    /// against the actual shape.
+    pub fn query(&self, min: Vec2, max: Vec2) -> Vec<Entity> {
+        let min = Self::cell(min - Vec2::splat(self.max_radius));
+        let max = Self::cell(max + Vec2::splat(self.max_radius));
+
+        let mut found = Vec::new();
+
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                if let Some(cell) = self.cells.get(&IVec2::new(x, y)) {
+                    found.extend_from_slice(cell);
+                }
+            }
+        }
+
+        found.sort_unstable_by_key(|e| e.rank);
+        found.into_iter().map(|e| e.entity).collect()
+    }
+
+    fn cell(pos: Vec2) -> IVec2 {
+        (pos / Self::CELL_SIZE).floor().as_ivec2()
+    }
+}
+
+pub fn build_spatial_index(
+    query: Query<(&GlobalTransform, &BoundingCircle)>,
+    targets_tree: Res<TargetingTree>,
+    mut spatial_index: ResMut<SpatialIndex>,
+) {
+    let SpatialIndex { cells, max_radius } = &mut *spatial_index;
+
+    for cell in cells.values_mut() {
+        cell.clear();
+    }
+
+    *max_radius = 0.0;
+
+    for (rank, entity) in targets_tree.iter().enumerate() {
+        let Ok((transform, bounding_circle)) = query.get(entity) else {
+            continue;
+        };
+
+        *max_radius = max_radius.max(bounding_circle.radius);
+
+        cells
+            .entry(SpatialIndex::cell(transform.translation().xy()))
+            .or_default()
+            .push(IndexedEntity { rank, entity });
+    }
+}
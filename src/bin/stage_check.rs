@@ -0,0 +1,235 @@
+//! Validates and inspects map files without starting the game.
+//!
+//! ```text
+//! stage_check [--assets <dir>] <map.ron|map.tmj>...
+//! ```
+//!
+//! Tiled maps (`.tmj`) are imported the same way the game imports them.
+//!
+//! Prints a summary of every map, its validation errors and its grid as
+//! ASCII. Exits with a non-zero status if any map failed to parse or
+//! validate, so it can be used in CI.
+
+use bevy::prelude::*;
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use spcc::loader::map::Map;
+use spcc::loader::tiled;
+use spcc::tile_map::nav::Pathfinder;
+use spcc::tile_map::TileKind;
+
+const USAGE: &str = "usage: stage_check [--assets <dir>] <map.ron|map.tmj>...";
+
+fn main() -> ExitCode {
+    let mut assets = PathBuf::from("assets");
+    let mut paths = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--assets" => {
+                let Some(dir) = args.next() else {
+                    eprintln!("{}", USAGE);
+                    return ExitCode::FAILURE;
+                };
+
+                assets = dir.into();
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    if paths.is_empty() {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    }
+
+    let mut ok = true;
+
+    for path in paths.iter() {
+        ok &= check(path, &assets);
+    }
+
+    if ok {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Checks a single map, returning `false` if it is invalid.
+fn check(path: &Path, assets: &Path) -> bool {
+    println!("== {}", path.display());
+
+    let is_tiled = path
+        .extension()
+        .map(|ext| ext == "tmj")
+        .unwrap_or_default();
+
+    let map = match std::fs::read(path)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| if is_tiled {
+            tiled::import(&bytes).map_err(|e| e.to_string())
+        } else {
+            ron::de::from_bytes::<Map>(&bytes).map_err(|e| e.to_string())
+        })
+    {
+        Ok(map) => map,
+        Err(e) => {
+            println!("error: {}", e);
+            println!();
+            return false;
+        }
+    };
+
+    print_summary(&map);
+    println!();
+    print_grid(&map);
+    println!();
+
+    let validation = map.validate(|model| assets.join(model).exists());
+
+    let ok = match validation {
+        Ok(()) => {
+            println!("no errors");
+            true
+        }
+        Err(errors) => {
+            for error in errors.iter() {
+                println!("error: {}", error);
+            }
+
+            false
+        }
+    };
+
+    println!();
+    ok
+}
+
+fn print_summary(map: &Map) {
+    let tiles = &map.tile_map.tiles;
+
+    println!("name: {}", map.name);
+    println!("tiles: {}", tiles.len());
+
+    let mut kinds = BTreeMap::<String, usize>::new();
+
+    for tile in tiles.iter() {
+        *kinds.entry(format!("{:?}", tile.kind)).or_default() += 1;
+    }
+
+    for (kind, count) in kinds.iter() {
+        println!("  {}: {}", kind, count);
+    }
+
+    let grid = map.grid();
+    let deployable = tiles
+        .iter()
+        .filter(|tile| {
+            grid.get(&tile.pos.into())
+                .map(|tile| tile.deployable())
+                .unwrap_or_default()
+        })
+        .count();
+
+    println!("deployable: {}", deployable);
+
+    println!("routes: {}", map.routes.len());
+
+    let pathfinder = Pathfinder::new(&grid);
+
+    for (idx, route) in map.routes.iter().enumerate() {
+        // path length in tiles, through every checkpoint
+        let length = std::iter::once(route.spawn)
+            .chain(route.checkpoints.iter().copied())
+            .collect::<Vec<_>>()
+            .windows(2)
+            .map(|w| {
+                pathfinder
+                    .find_path(w[0].into(), w[1].into())
+                    .ok()
+                    .map(|path| path.len() - 1)
+            })
+            .sum::<Option<usize>>();
+
+        let length = match length {
+            Some(length) => format!("{} tiles", length),
+            None => "unreachable".to_string(),
+        };
+
+        println!(
+            "  #{} \"{}\": ({}, {}) -> {} checkpoints, {}",
+            idx, route.name, route.spawn.x, route.spawn.y, route.checkpoints.len(), length,
+        );
+    }
+}
+
+/// Prints the grid, with `y` going up.
+///
+/// ```text
+/// .  ground          ,  deployable ground
+/// #  high ground     =  deployable high ground
+/// X  wall            O  hole
+/// S  spawn           C  checkpoint
+/// ```
+fn print_grid(map: &Map) {
+    let tiles = &map.tile_map.tiles;
+
+    if tiles.is_empty() {
+        return;
+    }
+
+    let min = tiles.iter().fold(IVec2::MAX, |min, tile| min.min(tile.pos));
+    let max = tiles.iter().fold(IVec2::MIN, |max, tile| max.max(tile.pos));
+
+    let spawns = map.routes
+        .iter()
+        .map(|route| route.spawn)
+        .collect::<HashSet<_>>();
+    let checkpoints = map.routes
+        .iter()
+        .flat_map(|route| route.checkpoints.iter().copied())
+        .collect::<HashSet<_>>();
+
+    let grid = map.grid();
+
+    for y in (min.y..=max.y).rev() {
+        let row = (min.x..=max.x)
+            .map(|x| {
+                let pos = IVec2::new(x, y);
+
+                if spawns.contains(&pos) {
+                    return 'S';
+                }
+
+                if checkpoints.contains(&pos) {
+                    return 'C';
+                }
+
+                let Some(tile) = grid.get(&pos.into()) else {
+                    return ' ';
+                };
+
+                match (tile.kind(), tile.deployable()) {
+                    (TileKind::Ground, false) => '.',
+                    (TileKind::Ground, true) => ',',
+                    (TileKind::HighGround, false) => '#',
+                    (TileKind::HighGround, true) => '=',
+                    (TileKind::Wall, _) => 'X',
+                    (TileKind::Hole, _) => 'O',
+                }
+            })
+            .collect::<String>();
+
+        println!("{:>4} {}", y, row.trim_end());
+    }
+}
@@ -0,0 +1,423 @@
+//! A crude in-game stage editor, only available with the `debug` feature.
+//!
+//! Press `F2` in a stage to toggle the editor. While it is open:
+//! * `1` picks the tile tool. Left click cycles a tile's [`TileKind`], right
+//!   click toggles whether it is deployable.
+//! * `2` picks the spawn tool. Left click places a spawn, starting a new
+//!   route, and switches to the route tool.
+//! * `3` picks the route tool. Left click adds a checkpoint to the selected
+//!   route, right click removes the last one.
+//! * `Tab` selects the next route, `Delete` removes the selected route.
+//! * `F5` saves the map back to where it was loaded from. Maps imported from
+//!   other formats are saved next to their source as `<name>.map.ron`.
+//!
+//! The editor closes whenever the stage is unloaded, restarted or replaced.
+
+use bevy::prelude::*;
+
+use std::path::{Path, PathBuf};
+
+use crate::AppState;
+use crate::loader::{LoadStageEvent, StageAssets};
+use crate::loader::teardown::{RestartStageEvent, UnloadStageEvent};
+use crate::loader::map::{Map, Route};
+use crate::material::TileHighlightMaterial;
+use crate::tile_map::focus::CursorTile;
+use crate::tile_map::nav::Pathfinder;
+use crate::tile_map::{self, Coordinates, Grid, Tile, TileKind};
+
+/// Editor plugin.
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_state::<EditorState>()
+            .init_resource::<EditorAssets>()
+            .add_systems(Startup, load_editor_assets)
+            .add_systems(Update, toggle_editor.run_if(in_state(AppState::InGame)))
+            .add_systems(OnEnter(EditorState::Enabled), open_editor)
+            .add_systems(OnExit(EditorState::Enabled), close_editor)
+            .add_systems(
+                Update,
+                (select_tool, edit_tiles, edit_routes, save_map, draw_routes, close_on_stage_change)
+                    .run_if(in_state(EditorState::Enabled)),
+            )
+            .add_systems(
+                PostUpdate,
+                show_tile_kinds
+                    .after(tile_map::highlight::composite_highlights)
+                    .run_if(in_state(EditorState::Enabled)),
+            );
+    }
+}
+
+/// Whether the editor is open.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, States)]
+pub enum EditorState {
+    #[default]
+    Disabled,
+    Enabled,
+}
+
+/// The tool the editor is using.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EditorTool {
+    #[default]
+    Tile,
+    Spawn,
+    Route,
+}
+
+/// The map being edited.
+///
+/// Tiles are edited live on the [`Grid`], and only snapshotted into the map
+/// on save. Everything else is edited here.
+#[derive(Debug, Resource)]
+pub struct EditorMap {
+    pub map: Map,
+    pub tool: EditorTool,
+    pub selected_route: Option<usize>,
+}
+
+/// Materials for displaying tiles in the editor.
+#[derive(Default, Resource)]
+pub struct EditorAssets {
+    pub ground: Handle<TileHighlightMaterial>,
+    pub deployable_ground: Handle<TileHighlightMaterial>,
+    pub high_ground: Handle<TileHighlightMaterial>,
+    pub deployable_high_ground: Handle<TileHighlightMaterial>,
+    pub wall: Handle<TileHighlightMaterial>,
+    pub hole: Handle<TileHighlightMaterial>,
+}
+
+impl EditorAssets {
+    /// The material for a tile.
+    pub fn material(&self, tile: &Tile) -> &Handle<TileHighlightMaterial> {
+        match (tile.kind(), tile.deployable()) {
+            (TileKind::Ground, false) => &self.ground,
+            (TileKind::Ground, true) => &self.deployable_ground,
+            (TileKind::HighGround, false) => &self.high_ground,
+            (TileKind::HighGround, true) => &self.deployable_high_ground,
+            (TileKind::Wall, _) => &self.wall,
+            (TileKind::Hole, _) => &self.hole,
+        }
+    }
+}
+
+pub fn load_editor_assets(
+    mut editor_assets: ResMut<EditorAssets>,
+    mut tile_materials: ResMut<Assets<TileHighlightMaterial>>,
+) {
+    let mut material = |color| tile_materials.add(TileHighlightMaterial {
+        color,
+        color_texture: None,
+        animate_speed: 0.0,
+    });
+
+    *editor_assets = EditorAssets {
+        ground: material(Color::rgba(0.6, 0.6, 0.6, 0.5)),
+        deployable_ground: material(Color::rgba(0.4, 0.9, 0.4, 0.5)),
+        high_ground: material(Color::rgba(0.3, 0.3, 0.6, 0.5)),
+        deployable_high_ground: material(Color::rgba(0.3, 0.6, 0.9, 0.5)),
+        wall: material(Color::rgba(0.1, 0.1, 0.1, 0.8)),
+        hole: material(Color::rgba(0.8, 0.1, 0.1, 0.8)),
+    };
+}
+
+pub fn toggle_editor(
+    keys: Res<Input<KeyCode>>,
+    state: Res<State<EditorState>>,
+    mut next_state: ResMut<NextState<EditorState>>,
+) {
+    if keys.just_pressed(KeyCode::F2) {
+        next_state.set(match state.get() {
+            EditorState::Disabled => EditorState::Enabled,
+            EditorState::Enabled => EditorState::Disabled,
+        });
+    }
+}
+
+pub fn open_editor(
+    mut commands: Commands,
+    stage_assets: Res<StageAssets>,
+    maps: Res<Assets<Map>>,
+    mut next_state: ResMut<NextState<EditorState>>,
+) {
+    let Some(map) = maps.get(stage_assets.map()) else {
+        warn!("no map loaded to edit");
+        next_state.set(EditorState::Disabled);
+        return;
+    };
+
+    commands.insert_resource(EditorMap {
+        map: map.clone(),
+        tool: EditorTool::default(),
+        selected_route: None,
+    });
+}
+
+pub fn close_editor(mut commands: Commands) {
+    commands.remove_resource::<EditorMap>();
+}
+
+/// Closes the editor when the stage changes, so edits to one stage are never
+/// saved over another.
+pub fn close_on_stage_change(
+    mut unload_stage_rx: EventReader<UnloadStageEvent>,
+    mut restart_stage_rx: EventReader<RestartStageEvent>,
+    mut load_stage_rx: EventReader<LoadStageEvent>,
+    mut next_state: ResMut<NextState<EditorState>>,
+) {
+    let changed = unload_stage_rx.iter().count() > 0
+        || restart_stage_rx.iter().count() > 0
+        || load_stage_rx.iter().count() > 0;
+
+    if changed {
+        next_state.set(EditorState::Disabled);
+    }
+}
+
+pub fn select_tool(
+    keys: Res<Input<KeyCode>>,
+    mut editor: ResMut<EditorMap>,
+) {
+    if keys.just_pressed(KeyCode::Key1) {
+        editor.tool = EditorTool::Tile;
+    }
+
+    if keys.just_pressed(KeyCode::Key2) {
+        editor.tool = EditorTool::Spawn;
+    }
+
+    if keys.just_pressed(KeyCode::Key3) {
+        editor.tool = EditorTool::Route;
+    }
+
+    let route_count = editor.map.routes.len();
+
+    if keys.just_pressed(KeyCode::Tab) && route_count > 0 {
+        editor.selected_route = Some(editor.selected_route
+            .map(|idx| (idx + 1) % route_count)
+            .unwrap_or_default());
+    }
+
+    if keys.just_pressed(KeyCode::Delete) {
+        if let Some(idx) = editor.selected_route.take() {
+            editor.map.routes.remove(idx);
+        }
+    }
+}
+
+pub fn edit_tiles(
+    editor: Res<EditorMap>,
+    mouse: Res<Input<MouseButton>>,
+    cursor: CursorTile,
+    mut tile_query: Query<&mut Tile>,
+) {
+    if editor.tool != EditorTool::Tile {
+        return;
+    }
+
+    let cycle = mouse.just_pressed(MouseButton::Left);
+    let toggle = mouse.just_pressed(MouseButton::Right);
+
+    if !cycle && !toggle {
+        return;
+    }
+
+    let Some((grid, coordinates)) = cursor.get() else {
+        return;
+    };
+
+    let Some(mut tile) = grid
+        .get(&coordinates)
+        .and_then(|tile| tile_query.get_mut(tile.entity).ok())
+    else {
+        return;
+    };
+
+    if cycle {
+        let kind = next_kind(tile.kind());
+        tile.set_kind(kind);
+    }
+
+    if toggle {
+        let deployable = tile.allows_deployment();
+        tile.set_deployable(!deployable);
+    }
+}
+
+pub fn edit_routes(
+    mut editor: ResMut<EditorMap>,
+    mouse: Res<Input<MouseButton>>,
+    cursor: CursorTile,
+) {
+    let place = mouse.just_pressed(MouseButton::Left);
+    let undo = mouse.just_pressed(MouseButton::Right);
+
+    match editor.tool {
+        EditorTool::Spawn if place => {
+            let Some((_, coordinates)) = cursor.get() else {
+                return;
+            };
+
+            let idx = editor.map.routes.len();
+
+            editor.map.routes.push(Route {
+                name: format!("route {}", idx),
+                spawn: *coordinates,
+                checkpoints: Vec::new(),
+            });
+
+            editor.selected_route = Some(idx);
+            editor.tool = EditorTool::Route;
+        }
+        EditorTool::Route if place || undo => {
+            let Some(idx) = editor.selected_route else {
+                return;
+            };
+
+            if place {
+                let Some((_, coordinates)) = cursor.get() else {
+                    return;
+                };
+
+                editor.map.routes[idx].checkpoints.push(*coordinates);
+            } else {
+                editor.map.routes[idx].checkpoints.pop();
+            }
+        }
+        _ => (),
+    }
+}
+
+pub fn save_map(
+    keys: Res<Input<KeyCode>>,
+    editor: Res<EditorMap>,
+    grid_query: Query<(&Grid, &Transform)>,
+    stage_assets: Res<StageAssets>,
+    asset_server: Res<AssetServer>,
+) {
+    if !keys.just_pressed(KeyCode::F5) {
+        return;
+    }
+
+    let Ok((grid, grid_transform)) = grid_query.get_single() else {
+        return;
+    };
+
+    let Some(path) = asset_server.get_handle_path(stage_assets.map()) else {
+        warn!("map was not loaded from a file, cannot save");
+        return;
+    };
+
+    let map = editor.map.snapshot(grid, grid_transform.translation);
+
+    // save anyways, the map may be a work in progress
+    let validation = map.validate(|model| {
+        asset_server
+            .asset_io()
+            .get_metadata(Path::new(model))
+            .is_ok()
+    });
+
+    if let Err(errors) = validation {
+        for error in errors {
+            warn!("saving invalid map: {}", error);
+        }
+    }
+
+    let path = save_path(path.path());
+
+    let res = ron::ser::to_string_pretty(&map, default())
+        .map_err(|e| e.to_string())
+        .and_then(|ron| {
+            std::fs::write(Path::new("assets").join(&path), ron)
+                .map_err(|e| e.to_string())
+        });
+
+    match res {
+        Ok(()) => info!("saved map to {}", path.display()),
+        Err(e) => error!("failed to save map: {}", e),
+    }
+}
+
+/// Draws every route along the path enemies will take.
+pub fn draw_routes(
+    editor: Res<EditorMap>,
+    grid_query: Query<(&Grid, &GlobalTransform)>,
+    mut gizmos: Gizmos,
+) {
+    let Ok((grid, grid_transform)) = grid_query.get_single() else {
+        return;
+    };
+
+    let pathfinder = Pathfinder::new(grid);
+    let to_world = |pos: IVec2| {
+        grid_transform.transform_point(Coordinates::from(pos).local(0.05))
+    };
+
+    for (idx, route) in editor.map.routes.iter().enumerate() {
+        let color = if editor.selected_route == Some(idx) {
+            Color::YELLOW
+        } else {
+            Color::ORANGE
+        };
+
+        gizmos.circle(to_world(route.spawn), Vec3::Z, 0.4, color);
+
+        let mut from = route.spawn;
+
+        for &to in route.checkpoints.iter() {
+            gizmos.circle(to_world(to), Vec3::Z, 0.2, color);
+
+            match pathfinder.find_path(from.into(), to.into()) {
+                Ok(path) => gizmos.linestrip(path.into_iter().map(|c| to_world(*c)), color),
+                Err(_) => gizmos.line(to_world(from), to_world(to), Color::RED),
+            }
+
+            from = to;
+        }
+    }
+}
+
+/// Colors tiles by their kind, over any other highlights.
+pub fn show_tile_kinds(
+    mut tile_query: Query<(&Tile, &mut Handle<TileHighlightMaterial>)>,
+    editor_assets: Res<EditorAssets>,
+) {
+    for (tile, mut material) in tile_query.iter_mut() {
+        let new_material = editor_assets.material(tile);
+
+        if *material != *new_material {
+            *material = new_material.clone();
+        }
+    }
+}
+
+/// Where a map loaded from `source` is saved.
+///
+/// Maps are always saved as RON, so maps imported from anything else are
+/// saved next to their source instead of overwriting it.
+fn save_path(source: &Path) -> PathBuf {
+    if source.extension().map_or(false, |ext| ext == "ron") {
+        return source.to_path_buf();
+    }
+
+    let stem = source
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+
+    source.with_file_name(format!("{}.map.ron", stem))
+}
+
+fn next_kind(kind: TileKind) -> TileKind {
+    match kind {
+        TileKind::Ground => TileKind::HighGround,
+        TileKind::HighGround => TileKind::Wall,
+        TileKind::Wall => TileKind::Hole,
+        TileKind::Hole => TileKind::Ground,
+    }
+}
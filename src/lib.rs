@@ -1,6 +1,8 @@
 #![feature(div_duration)]
 
 pub mod battle;
+#[cfg(feature = "debug")]
+pub mod editor;
 pub mod tile_map;
 pub mod loader;
 pub mod material;
@@ -6,24 +6,31 @@
 //! aren't typically things that can be directly varied by the player, unlike
 //! selected contracts, operators and event-specific tools.
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde::de::{self, Deserializer, Visitor};
+use serde::ser::Serializer;
 
-use std::fmt::{self, Formatter};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::path::Path;
 
 use bevy::reflect::{TypeUuid, TypePath};
 use bevy::prelude::*;
 
 use iyes_progress::prelude::*;
 
-use crate::tile_map::{self, GridBundle, TileBundle, TileKind};
+use crate::AppState;
+use crate::tile_map::{self, Grid, GridBundle, TileBundle, TileEffect, TileKind};
+use crate::tile_map::nav::Pathfinder;
 
 use super::StageAssets;
+use super::teardown::StageEntity;
 
 /// A map.
 ///
-/// Can be loaded from RON.
-#[derive(Debug, Clone, Deserialize, TypeUuid, TypePath)]
+/// Can be loaded from and saved to RON.
+#[derive(Debug, Clone, Deserialize, PartialEq, Serialize, TypeUuid, TypePath)]
 #[uuid = "909141d1-0a85-4833-8b94-7164332c2bf4"]
 pub struct Map {
     /// The name of the map.
@@ -36,13 +43,16 @@ pub struct Map {
     pub tile_map: TileMap,
     /// Static models.
     pub models: Vec<Model>,
+    /// The routes enemies can take through the map.
+    #[serde(default)]
+    pub routes: Vec<Route>,
 }
 
 /// Environmental display settings for maps.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq, Serialize)]
 pub struct Environment {
     /// The color of the directional light.
-    #[serde(deserialize_with = "from_hex")]
+    #[serde(deserialize_with = "from_hex", serialize_with = "to_hex")]
     pub color: Color,
     /// The luminance (lux) of the directional light.
     ///
@@ -53,7 +63,7 @@ pub struct Environment {
 }
 
 /// Tile map settings.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq, Serialize)]
 pub struct TileMap {
     /// The offset of the tilemap.
     pub offset: Vec3,
@@ -62,7 +72,7 @@ pub struct TileMap {
 }
 
 /// Definition for a single tile.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq, Serialize)]
 pub struct Tile {
     /// Position of a tile.
     pub pos: IVec2,
@@ -72,10 +82,70 @@ pub struct Tile {
     /// Whether the tile is deployable or not.
     #[serde(default)]
     pub deployable: bool,
+    /// Whether ranged operators can deploy on the tile, even if it is
+    /// [`TileKind::Ground`].
+    #[serde(default)]
+    pub ranged_deployable: bool,
+    /// Effects applied to units standing on the tile.
+    #[serde(default)]
+    pub effects: Vec<TileEffect>,
+}
+
+impl TileMap {
+    /// Creates a `TileMap` from a live [`Grid`].
+    ///
+    /// Tiles are sorted by row, then column, so snapshots of the same grid
+    /// are always the same.
+    pub fn from_grid(grid: &Grid, offset: Vec3) -> TileMap {
+        let mut tiles = grid
+            .iter()
+            .map(|(coordinates, tile)| Tile::from_tile(**coordinates, tile))
+            .collect::<Vec<_>>();
+
+        tiles.sort_by_key(|tile| (tile.pos.y, tile.pos.x));
+
+        TileMap {
+            offset,
+            tiles,
+        }
+    }
+}
+
+impl Tile {
+    /// Creates a definition from a [`tile_map::Tile`] at `pos`.
+    pub fn from_tile(pos: IVec2, tile: &tile_map::Tile) -> Tile {
+        Tile {
+            pos,
+            kind: tile.kind(),
+            deployable: tile.allows_deployment(),
+            ranged_deployable: tile.allows_ranged_deployment(),
+            effects: tile.effects().to_vec(),
+        }
+    }
+
+    /// Creates the [`tile_map::Tile`] for this definition.
+    pub fn to_tile(&self) -> tile_map::Tile {
+        tile_map::Tile::new(self.kind, self.deployable)
+            .with_ranged_deployment(self.ranged_deployable)
+            .with_effects(self.effects.clone())
+    }
+}
+
+/// A route enemies can take through the map.
+#[derive(Debug, Clone, Deserialize, PartialEq, Serialize)]
+pub struct Route {
+    /// The name of the route.
+    #[serde(default)]
+    pub name: String,
+    /// The tile enemies on this route spawn on.
+    pub spawn: IVec2,
+    /// The tiles enemies on this route walk to, in order.
+    #[serde(default)]
+    pub checkpoints: Vec<IVec2>,
 }
 
 /// A static model for a map.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq, Serialize)]
 pub struct Model {
     pub path: String,
     #[serde(default)]
@@ -107,6 +177,227 @@ where
     deserializer.deserialize_string(HexVisitor)
 }
 
+fn to_hex<S>(color: &Color, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let [r, g, b, a] = color
+        .as_rgba_f32()
+        .map(|c| (c.clamp(0.0, 1.0) * u8::MAX as f32).round() as u8);
+
+    // leave out alpha if it's opaque, like most hex colors
+    let hex = if a == u8::MAX {
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    } else {
+        format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+    };
+
+    serializer.serialize_str(&hex)
+}
+
+impl Map {
+    /// Snapshots a live [`Grid`] into a copy of this map, replacing its
+    /// tiles.
+    ///
+    /// Everything else, like the environment, models and routes, is kept
+    /// from this map.
+    pub fn snapshot(&self, grid: &Grid, offset: Vec3) -> Map {
+        Map {
+            tile_map: TileMap::from_grid(grid, offset),
+            ..self.clone()
+        }
+    }
+
+    /// Creates a [`Grid`] from the map's tiles, without spawning anything.
+    pub fn grid(&self) -> Grid {
+        Grid::from_tiles(self.tile_map.tiles
+            .iter()
+            .map(|tile| (tile.pos.into(), tile.to_tile())))
+    }
+
+    /// Checks the map for errors that would otherwise load a broken stage.
+    ///
+    /// `model_exists` is called with the path of every model, without its
+    /// label, and should check if the file exists.
+    ///
+    /// Every error found is returned, not just the first one.
+    pub fn validate(&self, model_exists: impl Fn(&str) -> bool) -> Result<(), Vec<MapError>> {
+        let mut errors = Vec::new();
+
+        // duplicate tiles
+        let mut positions = HashMap::<IVec2, usize>::new();
+
+        for (idx, tile) in self.tile_map.tiles.iter().enumerate() {
+            if let Some(first) = positions.insert(tile.pos, idx) {
+                errors.push(MapError::DuplicateTile {
+                    pos: tile.pos,
+                    first,
+                    second: idx,
+                });
+            }
+        }
+
+        // routes
+        let grid = self.grid();
+        let pathfinder = Pathfinder::new(&grid);
+
+        for (route_idx, route) in self.routes.iter().enumerate() {
+            let spawn_walkable = match grid.get(&route.spawn.into()) {
+                Some(tile) if tile.is_solid() => {
+                    errors.push(MapError::SpawnOnSolidTile {
+                        route: route_idx,
+                        pos: route.spawn,
+                    });
+                    false
+                }
+                Some(tile) if !tile.is_walkable() => {
+                    errors.push(MapError::SpawnNotWalkable {
+                        route: route_idx,
+                        pos: route.spawn,
+                    });
+                    false
+                }
+                Some(_) => true,
+                None => {
+                    errors.push(MapError::SpawnOffGrid {
+                        route: route_idx,
+                        pos: route.spawn,
+                    });
+                    false
+                }
+            };
+
+            // paths from an invalid spawn are meaningless
+            if !spawn_walkable {
+                continue;
+            }
+
+            let mut from = route.spawn;
+
+            for (checkpoint_idx, &to) in route.checkpoints.iter().enumerate() {
+                let reachable = grid
+                    .get(&to.into())
+                    .map(|tile| tile.is_walkable())
+                    .unwrap_or_default()
+                    && pathfinder.find_path(from.into(), to.into()).is_ok();
+
+                if !reachable {
+                    errors.push(MapError::UnreachableCheckpoint {
+                        route: route_idx,
+                        checkpoint: checkpoint_idx,
+                        from,
+                        to,
+                    });
+                    break;
+                }
+
+                from = to;
+            }
+        }
+
+        // models
+        for (idx, model) in self.models.iter().enumerate() {
+            let path = model.path
+                .split('#')
+                .next()
+                .unwrap_or_default();
+
+            if !model_exists(path) {
+                errors.push(MapError::MissingModel {
+                    model: idx,
+                    path: model.path.clone(),
+                });
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// An error found by [`Map::validate`].
+///
+/// Indices point into the lists of the [`Map`] the error was found in.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MapError {
+    /// Two tiles share a position.
+    DuplicateTile {
+        pos: IVec2,
+        first: usize,
+        second: usize,
+    },
+    /// A route spawns enemies on a solid tile.
+    SpawnOnSolidTile {
+        route: usize,
+        pos: IVec2,
+    },
+    /// A route spawns enemies on a tile they cannot walk on, like a hole.
+    SpawnNotWalkable {
+        route: usize,
+        pos: IVec2,
+    },
+    /// A route spawns enemies on a position with no tile.
+    SpawnOffGrid {
+        route: usize,
+        pos: IVec2,
+    },
+    /// A checkpoint of a route cannot be reached from the one before it (or
+    /// the spawn, for the first checkpoint).
+    UnreachableCheckpoint {
+        route: usize,
+        checkpoint: usize,
+        from: IVec2,
+        to: IVec2,
+    },
+    /// A model file does not exist.
+    MissingModel {
+        model: usize,
+        path: String,
+    },
+}
+
+impl Display for MapError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            MapError::DuplicateTile { pos, first, second } => write!(
+                f,
+                "tiles #{} and #{} are both at ({}, {})",
+                first, second, pos.x, pos.y,
+            ),
+            MapError::SpawnOnSolidTile { route, pos } => write!(
+                f,
+                "route #{} spawns on solid tile ({}, {})",
+                route, pos.x, pos.y,
+            ),
+            MapError::SpawnNotWalkable { route, pos } => write!(
+                f,
+                "route #{} spawns on unwalkable tile ({}, {})",
+                route, pos.x, pos.y,
+            ),
+            MapError::SpawnOffGrid { route, pos } => write!(
+                f,
+                "route #{} spawns at ({}, {}), which has no tile",
+                route, pos.x, pos.y,
+            ),
+            MapError::UnreachableCheckpoint { route, checkpoint, from, to } => write!(
+                f,
+                "checkpoint #{} of route #{} at ({}, {}) cannot be reached from ({}, {})",
+                checkpoint, route, to.x, to.y, from.x, from.y,
+            ),
+            MapError::MissingModel { model, path } => write!(
+                f,
+                "model #{} \"{}\" does not exist",
+                model, path,
+            ),
+        }
+    }
+}
+
+impl Error for MapError {}
+
 /// The entity that contains all static models and the grid of a map.
 ///
 /// It is only valid if there is one or zero `MapInstance`.
@@ -121,6 +412,7 @@ pub fn load_map(
     asset_server: Res<AssetServer>,
     mut loading: ResMut<AssetsLoading>,
     map_instance_query: Query<Entity, With<MapInstance>>,
+    mut app_state: ResMut<NextState<AppState>>,
 ) {
     // stop loading if it has already been loaded
     if let Ok(_) = map_instance_query.get_single() {
@@ -133,11 +425,34 @@ pub fn load_map(
         None => return,
     };
 
+    // validate before spawning anything
+    let validation = map.validate(|path| {
+        asset_server
+            .asset_io()
+            .get_metadata(Path::new(path))
+            .is_ok()
+    });
+
+    if let Err(errors) = validation {
+        for error in errors.iter() {
+            error!("invalid map \"{}\": {}", map.name, error);
+        }
+
+        error!(
+            "map \"{}\" failed validation with {} error(s), returning to splash",
+            map.name, errors.len(),
+        );
+
+        app_state.set(AppState::Splash);
+        return;
+    }
+
     // begin loading static models with root entity.
     let map_entity = commands
         .spawn((
             SpatialBundle::default(),
             MapInstance,
+            StageEntity,
         ))
         .id();
 
@@ -167,7 +482,7 @@ pub fn load_map(
                 parent
                     .spawn(TileBundle {
                         coordinates: tile.pos.clone().into(),
-                        tile: tile_map::Tile::new(tile.kind, tile.deployable),
+                        tile: tile.to_tile(),
                         ..default()
                     });
             }
@@ -189,3 +504,149 @@ pub fn load_map(
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CCMAP: &str = include_str!("../../assets/maps/ccmap.ron");
+
+    /// A map of a single row of tiles, starting at `(0, 0)`.
+    fn row_map(kinds: &[TileKind]) -> Map {
+        let tiles = kinds
+            .iter()
+            .enumerate()
+            .map(|(x, &kind)| Tile {
+                pos: IVec2::new(x as i32, 0),
+                kind,
+                deployable: false,
+                ranged_deployable: false,
+                effects: Vec::new(),
+            })
+            .collect();
+
+        Map {
+            name: "test".into(),
+            environment: Environment {
+                color: Color::WHITE,
+                luminance: 0.0,
+            },
+            tile_map: TileMap {
+                offset: Vec3::ZERO,
+                tiles,
+            },
+            models: Vec::new(),
+            routes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let map = ron::from_str::<Map>(CCMAP).unwrap();
+
+        let saved = ron::ser::to_string_pretty(&map, default()).unwrap();
+        let loaded = ron::from_str::<Map>(&saved).unwrap();
+
+        assert_eq!(map, loaded);
+    }
+
+    #[test]
+    fn hex_color_output() {
+        let map = ron::from_str::<Map>(CCMAP).unwrap();
+        let saved = ron::to_string(&map).unwrap();
+
+        assert!(saved.contains("color:\"#5a6275\""));
+    }
+
+    #[test]
+    fn grid_snapshot() {
+        let map = ron::from_str::<Map>(CCMAP).unwrap();
+        let snapshot = map.snapshot(&map.grid(), map.tile_map.offset);
+
+        let mut tiles = map.tile_map.tiles.clone();
+        tiles.sort_by_key(|tile| (tile.pos.y, tile.pos.x));
+
+        assert_eq!(tiles, snapshot.tile_map.tiles);
+        assert_eq!(map.routes, snapshot.routes);
+    }
+
+    #[test]
+    fn validate_unreachable_route() {
+        let mut map = row_map(&[TileKind::Ground, TileKind::Wall, TileKind::Ground]);
+
+        map.routes.push(Route {
+            name: "blocked".into(),
+            spawn: IVec2::new(0, 0),
+            checkpoints: vec![IVec2::new(2, 0)],
+        });
+
+        assert_eq!(
+            map.validate(|_| true),
+            Err(vec![MapError::UnreachableCheckpoint {
+                route: 0,
+                checkpoint: 0,
+                from: IVec2::new(0, 0),
+                to: IVec2::new(2, 0),
+            }]),
+        );
+    }
+
+    #[test]
+    fn validate_spawn_on_hole() {
+        let mut map = row_map(&[TileKind::Hole, TileKind::Ground]);
+
+        map.routes.push(Route {
+            name: "pit".into(),
+            spawn: IVec2::new(0, 0),
+            checkpoints: vec![IVec2::new(1, 0)],
+        });
+
+        assert_eq!(
+            map.validate(|_| true),
+            Err(vec![MapError::SpawnNotWalkable {
+                route: 0,
+                pos: IVec2::new(0, 0),
+            }]),
+        );
+    }
+
+    #[test]
+    fn validate_out_of_bounds_checkpoint() {
+        let mut map = row_map(&[TileKind::Ground, TileKind::Ground]);
+
+        map.routes.push(Route {
+            name: "off grid".into(),
+            spawn: IVec2::new(0, 0),
+            checkpoints: vec![IVec2::new(1, 0), IVec2::new(5, 5)],
+        });
+
+        assert_eq!(
+            map.validate(|_| true),
+            Err(vec![MapError::UnreachableCheckpoint {
+                route: 0,
+                checkpoint: 1,
+                from: IVec2::new(1, 0),
+                to: IVec2::new(5, 5),
+            }]),
+        );
+    }
+
+    #[test]
+    fn validate_missing_model() {
+        let mut map = row_map(&[TileKind::Ground]);
+
+        map.models.push(Model {
+            path: "models/missing.glb#Scene0".into(),
+            position: (0.0, 0.0, 0.0),
+        });
+
+        assert_eq!(
+            map.validate(|path| path != "models/missing.glb"),
+            Err(vec![MapError::MissingModel {
+                model: 0,
+                path: "models/missing.glb#Scene0".into(),
+            }]),
+        );
+        assert_eq!(map.validate(|_| true), Ok(()));
+    }
+}
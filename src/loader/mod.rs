@@ -5,8 +5,13 @@
 //! code is "hack quality" at best.
 
 pub mod map;
+pub mod teardown;
+pub mod tiled;
+pub mod unit;
 
 use map::Map;
+use teardown::{RestartStageEvent, UnloadStageEvent};
+use unit::{EnemyDef, OperatorDef};
 
 use bevy::prelude::*;
 
@@ -26,15 +31,30 @@ impl Plugin for LoaderPlugin {
                 .continue_to(AppState::InGame)
                 .track_assets())
             .add_event::<LoadStageEvent>()
+            .add_event::<UnloadStageEvent>()
+            .add_event::<RestartStageEvent>()
             .init_resource::<StageAssets>()
-            .add_systems(Update, begin_loading_stage)
+            .add_systems(Update,
+                (
+                    teardown::unload_stage,
+                    teardown::restart_stage,
+                    teardown::unload_stage_before_loading,
+                    begin_loading_stage,
+                ).chain(),
+            )
+            .add_systems(Update, (unit::spawn_enemies, unit::spawn_operators))
             .add_systems(Update,
                 // all loading systems
                 (
                     map::load_map,
                 ).run_if(in_state(AppState::StageLoading)),
             )
-            .add_plugins(RonAssetPlugin::<Map>::new(&["ron"]));
+            .add_plugins((
+                RonAssetPlugin::<Map>::new(&["ron"]),
+                RonAssetPlugin::<unit::EnemyDef>::new(&["enemy.ron"]),
+                RonAssetPlugin::<unit::OperatorDef>::new(&["operator.ron"]),
+            ))
+            .add_asset_loader(tiled::TiledMapLoader);
     }
 }
 
@@ -42,10 +62,14 @@ impl Plugin for LoaderPlugin {
 ///
 /// Does not actually do anything on its own, but can be passed as an argument
 /// to a loader.
-#[derive(Debug, Default, Event)]
+#[derive(Clone, Debug, Default, Event)]
 pub struct StageBuilder {
     /// The map as a path.
     map_path: String,
+    /// The operators brought into the stage.
+    squad: Vec<SquadMember>,
+    /// The enemy defs the stage needs, as paths.
+    enemy_paths: Vec<String>,
 }
 
 impl StageBuilder {
@@ -53,8 +77,57 @@ impl StageBuilder {
     pub fn new(map_path: impl Into<String>) -> StageBuilder {
         StageBuilder {
             map_path: map_path.into(),
+            ..default()
         }
     }
+
+    /// Adds an operator to the squad.
+    pub fn with_operator(mut self, member: SquadMember) -> StageBuilder {
+        self.squad.push(member);
+        self
+    }
+
+    /// Adds an enemy def the stage needs.
+    pub fn with_enemy(mut self, enemy_path: impl Into<String>) -> StageBuilder {
+        self.enemy_paths.push(enemy_path.into());
+        self
+    }
+}
+
+/// An operator brought into a stage.
+#[derive(Clone, Debug)]
+pub struct SquadMember {
+    /// The operator def as a path.
+    def_path: String,
+    /// The level of the operator.
+    level: u32,
+    /// Which of the operator's skills is brought, if any.
+    skill: Option<usize>,
+}
+
+impl SquadMember {
+    /// Creates a new squad member at level 1, without a skill.
+    pub fn new(def_path: impl Into<String>) -> SquadMember {
+        SquadMember {
+            def_path: def_path.into(),
+            level: 1,
+            skill: None,
+        }
+    }
+
+    /// Sets the level of the operator.
+    pub fn with_level(mut self, level: u32) -> SquadMember {
+        self.level = level;
+        self
+    }
+
+    /// Picks one of the operator's [skills][1] to bring.
+    ///
+    /// [1]: unit::OperatorDef::skills
+    pub fn with_skill(mut self, skill: usize) -> SquadMember {
+        self.skill = Some(skill);
+        self
+    }
 }
 
 /// Wad of assets for the current loaded stage.
@@ -62,6 +135,39 @@ impl StageBuilder {
 pub struct StageAssets {
     /// The specification of the currently loaded map.
     map: Handle<Map>,
+    /// The operators brought into the stage.
+    squad: Vec<SquadAssets>,
+    /// The enemy defs the stage needs.
+    enemies: Vec<Handle<EnemyDef>>,
+    /// The builder the stage was loaded with, used for restarts.
+    builder: Option<StageBuilder>,
+}
+
+impl StageAssets {
+    /// The specification of the currently loaded map.
+    pub fn map(&self) -> &Handle<Map> {
+        &self.map
+    }
+
+    /// The operators brought into the stage, in the order they were added to
+    /// the [`StageBuilder`].
+    pub fn squad(&self) -> &[SquadAssets] {
+        &self.squad
+    }
+
+    /// The enemy defs the stage needs, in the order they were added to the
+    /// [`StageBuilder`].
+    pub fn enemies(&self) -> &[Handle<EnemyDef>] {
+        &self.enemies
+    }
+}
+
+/// A loaded [`SquadMember`].
+#[derive(Clone, Debug)]
+pub struct SquadAssets {
+    pub def: Handle<OperatorDef>,
+    pub level: u32,
+    pub skill: Option<usize>,
 }
 
 /// The event that triggers a stage load.
@@ -70,7 +176,7 @@ pub struct StageAssets {
 /// complicated to detail the implementation.
 ///
 /// If this event is sent twice in a single frame, only the first one is
-/// processed.
+/// processed. If a stage is already loaded, it is torn down first.
 #[derive(Debug, Event)]
 pub struct LoadStageEvent(pub StageBuilder);
 
@@ -99,13 +205,41 @@ pub fn begin_loading_stage(
     // start loading the map
     let StageAssets {
         map,
-        ..
+        squad,
+        enemies,
+        builder,
     } = &mut *stage_assets;
 
+    *builder = Some(stage_builder.clone());
+
     *map = asset_server.load(&stage_builder.map_path);
     loading.add(&*map);
 
-    // load operators, contingencies:
+    // load operators
+    *squad = stage_builder.squad
+        .iter()
+        .map(|member| {
+            let def = asset_server.load(&member.def_path);
+            loading.add(&def);
+
+            SquadAssets {
+                def,
+                level: member.level,
+                skill: member.skill,
+            }
+        })
+        .collect();
+
+    // load enemies
+    *enemies = stage_builder.enemy_paths
+        .iter()
+        .map(|path| {
+            let def = asset_server.load(path);
+            loading.add(&def);
+            def
+        })
+        .collect();
+
+    // contingencies:
     // lol
 }
-
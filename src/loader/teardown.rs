@@ -0,0 +1,127 @@
+//! Stage teardown.
+//!
+//! Everything a stage spawns is despawned here, so another stage (or the same
+//! one) can be loaded without restarting the app.
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use crate::AppState;
+use crate::battle::targeting::{SpatialIndex, TargetingTree};
+use crate::ui::StatusBar;
+
+use super::map::MapInstance;
+use super::{LoadStageEvent, StageAssets};
+
+/// The event that unloads the current stage.
+#[derive(Debug, Default, Event)]
+pub struct UnloadStageEvent;
+
+/// The event that restarts the current stage, by loading it again with the
+/// same [`StageBuilder`][1].
+///
+/// [1]: super::StageBuilder
+#[derive(Debug, Default, Event)]
+pub struct RestartStageEvent;
+
+/// Marks an entity spawned by a stage, to be despawned with it.
+///
+/// Only root entities need this, their children are despawned with them. It
+/// should be added when the entity is spawned, not once its assets load, or
+/// the entity can outlive its stage.
+#[derive(Clone, Component, Debug, Default)]
+pub struct StageEntity;
+
+/// A system param for tearing down the current stage.
+#[derive(SystemParam)]
+pub struct StageTeardown<'w, 's> {
+    commands: Commands<'w, 's>,
+    map_query: Query<'w, 's, Entity, With<MapInstance>>,
+    // operators are parented to the grid, and skills to their operator, so
+    // only the roots need to be despawned
+    stage_query: Query<'w, 's, Entity, (With<StageEntity>, Without<Parent>)>,
+    status_bar_query: Query<'w, 's, Entity, With<StatusBar>>,
+    stage_assets: ResMut<'w, StageAssets>,
+    targeting_tree: ResMut<'w, TargetingTree>,
+    spatial_index: ResMut<'w, SpatialIndex>,
+}
+
+impl<'w, 's> StageTeardown<'w, 's> {
+    /// Checks if there is a stage to tear down.
+    pub fn is_loaded(&self) -> bool {
+        !self.map_query.is_empty() || self.stage_assets.builder.is_some()
+    }
+
+    /// Despawns every [`StageEntity`] and status bar, and resets all stage
+    /// resources.
+    ///
+    /// The despawns are deferred like any other command, so the stage is only
+    /// actually gone once commands are applied.
+    pub fn teardown(&mut self) {
+        let entities = self.stage_query
+            .iter()
+            .chain(self.status_bar_query.iter());
+
+        for entity in entities {
+            self.commands
+                .entity(entity)
+                .despawn_recursive();
+        }
+
+        *self.stage_assets = default();
+        *self.targeting_tree = default();
+        *self.spatial_index = default();
+    }
+}
+
+/// Unloads the current stage on [`UnloadStageEvent`].
+pub fn unload_stage(
+    mut unload_stage_rx: EventReader<UnloadStageEvent>,
+    mut teardown: StageTeardown,
+    mut app_state: ResMut<NextState<AppState>>,
+) {
+    if unload_stage_rx.iter().next().is_none() {
+        return;
+    }
+
+    unload_stage_rx.clear();
+
+    if teardown.is_loaded() {
+        teardown.teardown();
+        app_state.set(AppState::Splash);
+    }
+}
+
+/// Reloads the current stage on [`RestartStageEvent`].
+pub fn restart_stage(
+    mut restart_stage_rx: EventReader<RestartStageEvent>,
+    mut load_stage_tx: EventWriter<LoadStageEvent>,
+    stage_assets: Res<StageAssets>,
+) {
+    if restart_stage_rx.iter().next().is_none() {
+        return;
+    }
+
+    restart_stage_rx.clear();
+
+    if let Some(stage_builder) = &stage_assets.builder {
+        load_stage_tx.send(LoadStageEvent(stage_builder.clone()));
+    }
+}
+
+/// Tears down the current stage before a new one is loaded on
+/// [`LoadStageEvent`].
+pub fn unload_stage_before_loading(
+    mut load_stage_rx: EventReader<LoadStageEvent>,
+    mut teardown: StageTeardown,
+) {
+    if load_stage_rx.iter().next().is_none() {
+        return;
+    }
+
+    load_stage_rx.clear();
+
+    if teardown.is_loaded() {
+        teardown.teardown();
+    }
+}
@@ -0,0 +1,531 @@
+//! Importing maps made in [Tiled](https://www.mapeditor.org/).
+//!
+//! Only Tiled's JSON format (`.tmj`) with embedded tilesets is supported. The
+//! conversion goes like this:
+//!
+//! * **Tile layers** become tiles. Each tile takes the custom properties of
+//!   its tileset tile: `kind` (a [`TileKind`] name), `deployable` and
+//!   `ranged_deployable`. Empty cells are left out. If there are multiple tile
+//!   layers, the last layer with a tile at a position wins.
+//! * **Object layers** become routes. Polyline objects are routes, whose
+//!   first point is the spawn and whose other points are checkpoints. A route
+//!   with a `spawn` property spawns at the point object of that name instead,
+//!   so multiple routes can share a spawn.
+//! * **Map properties** fill in the rest: `name`, `color`, `luminance`,
+//!   `offset_x`, `offset_y`, `offset_z` and `model`.
+//!
+//! Tiled counts rows downwards, while the grid counts upwards, so rows are
+//! flipped on import.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::utils::BoxedFuture;
+use bevy::prelude::*;
+
+use crate::tile_map::TileKind;
+
+use super::map::{Environment, Map, Model, Route, Tile, TileMap};
+
+/// Flags Tiled stores in the upper bits of a gid.
+const GID_FLAGS: u32 = 0xF0000000;
+
+/// Loads `.tmj` files directly as a [`Map`].
+#[derive(Default)]
+pub struct TiledMapLoader;
+
+impl AssetLoader for TiledMapLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let map = import(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(map));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tmj"]
+    }
+}
+
+/// Converts a Tiled JSON map into a [`Map`].
+pub fn import(bytes: &[u8]) -> Result<Map, TiledError> {
+    let tiled = serde_json::from_slice::<TiledMap>(bytes)?;
+
+    tiled.into_map()
+}
+
+/// An error importing a Tiled map.
+#[derive(Debug)]
+pub enum TiledError {
+    /// The file is not a valid Tiled JSON map.
+    Json(serde_json::Error),
+    /// The map is infinite, which has no fixed grid.
+    Infinite,
+    /// A tileset is stored in a separate file.
+    ExternalTileset {
+        source: String,
+    },
+    /// A tile has a `kind` property that isn't a [`TileKind`].
+    UnknownKind {
+        gid: u32,
+        kind: String,
+    },
+    /// A route spawns at a point object that doesn't exist.
+    UnknownSpawn {
+        route: String,
+        spawn: String,
+    },
+    /// The `color` property of the map isn't a hex color.
+    InvalidColor(String),
+    /// A tile layer's data doesn't fit its size.
+    InvalidLayer {
+        width: i32,
+        height: i32,
+        len: usize,
+    },
+}
+
+impl Display for TiledError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            TiledError::Json(e) => write!(f, "invalid tiled map: {}", e),
+            TiledError::Infinite => f.write_str("infinite maps are not supported"),
+            TiledError::ExternalTileset { source } => write!(
+                f,
+                "tileset \"{}\" must be embedded in the map",
+                source,
+            ),
+            TiledError::UnknownKind { gid, kind } => write!(
+                f,
+                "tile {} has unknown kind \"{}\"",
+                gid, kind,
+            ),
+            TiledError::UnknownSpawn { route, spawn } => write!(
+                f,
+                "route \"{}\" spawns at \"{}\", which does not exist",
+                route, spawn,
+            ),
+            TiledError::InvalidColor(color) => write!(f, "invalid color \"{}\"", color),
+            TiledError::InvalidLayer { width, height, len } => write!(
+                f,
+                "tile layer is {}x{}, but has {} tiles",
+                width, height, len,
+            ),
+        }
+    }
+}
+
+impl Error for TiledError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TiledError::Json(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for TiledError {
+    fn from(e: serde_json::Error) -> TiledError {
+        TiledError::Json(e)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TiledMap {
+    width: i32,
+    height: i32,
+    tilewidth: f32,
+    tileheight: f32,
+    #[serde(default)]
+    infinite: bool,
+    #[serde(default)]
+    layers: Vec<Layer>,
+    #[serde(default)]
+    tilesets: Vec<Tileset>,
+    #[serde(default)]
+    properties: Vec<Property>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Layer {
+    TileLayer {
+        #[serde(default)]
+        data: Vec<u32>,
+        width: i32,
+        height: i32,
+    },
+    ObjectGroup {
+        #[serde(default)]
+        objects: Vec<Object>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct Tileset {
+    #[serde(default)]
+    firstgid: u32,
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default)]
+    tiles: Vec<TilesetTile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TilesetTile {
+    id: u32,
+    #[serde(default)]
+    properties: Vec<Property>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Object {
+    #[serde(default)]
+    name: String,
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    point: bool,
+    #[serde(default)]
+    polyline: Option<Vec<Point>>,
+    #[serde(default)]
+    properties: Vec<Property>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Point {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Debug, Deserialize)]
+struct Property {
+    name: String,
+    value: Value,
+}
+
+fn property<'a>(properties: &'a [Property], name: &str) -> Option<&'a Value> {
+    properties
+        .iter()
+        .find(|p| p.name == name)
+        .map(|p| &p.value)
+}
+
+impl TiledMap {
+    fn into_map(self) -> Result<Map, TiledError> {
+        if self.infinite {
+            return Err(TiledError::Infinite);
+        }
+
+        let tile_defs = self.tile_defs()?;
+
+        // tiles
+        let mut tiles = HashMap::<IVec2, Tile>::new();
+
+        for layer in self.layers.iter() {
+            let &Layer::TileLayer { ref data, width, height } = layer else {
+                continue;
+            };
+
+            if width <= 0 || height < 0 || data.len() as i64 != width as i64 * height as i64 {
+                return Err(TiledError::InvalidLayer {
+                    width,
+                    height,
+                    len: data.len(),
+                });
+            }
+
+            for (idx, gid) in data.iter().enumerate() {
+                let gid = gid & !GID_FLAGS;
+
+                if gid == 0 {
+                    continue;
+                }
+
+                let pos = self.to_grid(IVec2::new(idx as i32 % width, idx as i32 / width));
+                let mut tile = tile_defs.get(&gid).cloned().unwrap_or_else(|| Tile {
+                    pos,
+                    kind: TileKind::default(),
+                    deployable: false,
+                    ranged_deployable: false,
+                    effects: Vec::new(),
+                });
+                tile.pos = pos;
+
+                tiles.insert(pos, tile);
+            }
+        }
+
+        let mut tiles = tiles.into_values().collect::<Vec<_>>();
+        tiles.sort_by_key(|tile| (tile.pos.y, tile.pos.x));
+
+        // spawns and routes
+        let objects = self.layers
+            .iter()
+            .filter_map(|layer| match layer {
+                Layer::ObjectGroup { objects } => Some(objects),
+                _ => None,
+            })
+            .flatten();
+
+        let spawns = objects
+            .clone()
+            .filter(|object| object.point)
+            .map(|object| (object.name.as_str(), self.pixel_to_grid(object.x, object.y)))
+            .collect::<HashMap<_, _>>();
+
+        let mut routes = Vec::new();
+
+        for object in objects {
+            let Some(polyline) = &object.polyline else {
+                continue;
+            };
+
+            let mut points = polyline
+                .iter()
+                .map(|p| self.pixel_to_grid(object.x + p.x, object.y + p.y))
+                .collect::<Vec<_>>();
+
+            let spawn = match property(&object.properties, "spawn").and_then(Value::as_str) {
+                Some(spawn) => *spawns
+                    .get(spawn)
+                    .ok_or_else(|| TiledError::UnknownSpawn {
+                        route: object.name.clone(),
+                        spawn: spawn.to_string(),
+                    })?,
+                None if !points.is_empty() => points.remove(0),
+                None => continue,
+            };
+
+            routes.push(Route {
+                name: object.name.clone(),
+                spawn,
+                checkpoints: points,
+            });
+        }
+
+        // map properties
+        let name = property(&self.properties, "name")
+            .and_then(Value::as_str)
+            .unwrap_or("Untitled")
+            .to_string();
+
+        let color = match property(&self.properties, "color").and_then(Value::as_str) {
+            // tiled colors are #AARRGGBB
+            Some(color) => tiled_color(color)?,
+            None => Color::WHITE,
+        };
+
+        let luminance = property(&self.properties, "luminance")
+            .and_then(Value::as_f64)
+            .unwrap_or(1500.0) as f32;
+
+        let offset_property = |name| {
+            property(&self.properties, name)
+                .and_then(Value::as_f64)
+                .unwrap_or_default() as f32
+        };
+
+        let offset = Vec3::new(
+            offset_property("offset_x"),
+            offset_property("offset_y"),
+            offset_property("offset_z"),
+        );
+
+        let models = property(&self.properties, "model")
+            .and_then(Value::as_str)
+            .map(|path| Model {
+                path: path.to_string(),
+                position: (0.0, 0.0, 0.0),
+            })
+            .into_iter()
+            .collect();
+
+        Ok(Map {
+            name,
+            environment: Environment {
+                color,
+                luminance,
+            },
+            tile_map: TileMap {
+                offset,
+                tiles,
+            },
+            models,
+            routes,
+        })
+    }
+
+    /// Collects the tile definitions of every tileset by gid.
+    fn tile_defs(&self) -> Result<HashMap<u32, Tile>, TiledError> {
+        let mut defs = HashMap::new();
+
+        for tileset in self.tilesets.iter() {
+            if let Some(source) = &tileset.source {
+                return Err(TiledError::ExternalTileset {
+                    source: source.clone(),
+                });
+            }
+
+            for tile in tileset.tiles.iter() {
+                let gid = tileset.firstgid + tile.id;
+                let properties = &tile.properties;
+
+                let kind = match property(properties, "kind").and_then(Value::as_str) {
+                    Some(kind) => ron::from_str::<TileKind>(kind)
+                        .map_err(|_| TiledError::UnknownKind {
+                            gid,
+                            kind: kind.to_string(),
+                        })?,
+                    None => TileKind::default(),
+                };
+
+                let flag = |name| {
+                    property(properties, name)
+                        .and_then(Value::as_bool)
+                        .unwrap_or_default()
+                };
+
+                defs.insert(gid, Tile {
+                    pos: IVec2::ZERO,
+                    kind,
+                    deployable: flag("deployable"),
+                    ranged_deployable: flag("ranged_deployable"),
+                    effects: Vec::new(),
+                });
+            }
+        }
+
+        Ok(defs)
+    }
+
+    /// Converts a Tiled cell to grid coordinates.
+    fn to_grid(&self, cell: IVec2) -> IVec2 {
+        IVec2::new(cell.x, self.height - 1 - cell.y)
+    }
+
+    /// Converts a Tiled pixel position to the grid coordinates of the cell it
+    /// is in.
+    fn pixel_to_grid(&self, x: f32, y: f32) -> IVec2 {
+        self.to_grid(IVec2::new(
+            (x / self.tilewidth).floor() as i32,
+            (y / self.tileheight).floor() as i32,
+        ))
+    }
+}
+
+fn tiled_color(color: &str) -> Result<Color, TiledError> {
+    let hex = color.trim_start_matches('#');
+
+    // slicing below is by byte
+    if !hex.is_ascii() {
+        return Err(TiledError::InvalidColor(color.to_string()));
+    }
+
+    // move alpha to the end, where bevy expects it
+    let hex = match hex.len() {
+        8 => format!("{}{}", &hex[2..], &hex[..2]),
+        _ => hex.to_string(),
+    };
+
+    Color::hex(hex).map_err(|_| TiledError::InvalidColor(color.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAP: &str = r##"{
+        "width": 3,
+        "height": 2,
+        "tilewidth": 16,
+        "tileheight": 16,
+        "infinite": false,
+        "properties": [
+            { "name": "name", "type": "string", "value": "Test" },
+            { "name": "color", "type": "color", "value": "#ff5a6275" }
+        ],
+        "tilesets": [{
+            "firstgid": 1,
+            "tiles": [
+                { "id": 0, "properties": [
+                    { "name": "kind", "type": "string", "value": "Ground" },
+                    { "name": "deployable", "type": "bool", "value": true }
+                ] },
+                { "id": 1, "properties": [
+                    { "name": "kind", "type": "string", "value": "HighGround" }
+                ] }
+            ]
+        }],
+        "layers": [
+            { "type": "tilelayer", "width": 3, "height": 2, "data": [2, 0, 2, 1, 1, 1] },
+            { "type": "objectgroup", "objects": [
+                { "name": "a", "x": 40, "y": 24, "point": true },
+                { "name": "route", "x": 8, "y": 24, "polyline": [
+                    { "x": 0, "y": 0 }, { "x": 16, "y": 0 }
+                ], "properties": [
+                    { "name": "spawn", "type": "string", "value": "a" }
+                ] }
+            ] }
+        ]
+    }"##;
+
+    #[test]
+    fn import_map() {
+        let map = import(MAP.as_bytes()).unwrap();
+
+        assert_eq!(map.name, "Test");
+        assert_eq!(map.environment.color, Color::hex("5a6275").unwrap());
+
+        // rows are flipped
+        let high_ground = map.tile_map.tiles
+            .iter()
+            .filter(|tile| tile.kind == TileKind::HighGround)
+            .map(|tile| tile.pos)
+            .collect::<Vec<_>>();
+
+        assert_eq!(high_ground, [IVec2::new(0, 1), IVec2::new(2, 1)]);
+        assert_eq!(map.tile_map.tiles.len(), 5);
+        assert!(map.tile_map.tiles.iter().all(|tile| tile.deployable == (tile.kind == TileKind::Ground)));
+
+        assert_eq!(map.routes.len(), 1);
+        assert_eq!(map.routes[0].spawn, IVec2::new(2, 0));
+        assert_eq!(map.routes[0].checkpoints, [IVec2::new(0, 0), IVec2::new(1, 0)]);
+    }
+
+    #[test]
+    fn zero_width_layer() {
+        let map = MAP.replace(
+            r#""width": 3, "height": 2, "data""#,
+            r#""width": 0, "height": 2, "data""#,
+        );
+
+        assert!(matches!(
+            import(map.as_bytes()),
+            Err(TiledError::InvalidLayer { width: 0, height: 2, len: 6 }),
+        ));
+    }
+
+    #[test]
+    fn non_ascii_color() {
+        // 8 bytes, but not 8 characters
+        let map = MAP.replace("#ff5a6275", "#ff5a62\u{e9}");
+
+        assert!(matches!(
+            import(map.as_bytes()),
+            Err(TiledError::InvalidColor(_)),
+        ));
+    }
+}
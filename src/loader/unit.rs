@@ -0,0 +1,470 @@
+//! Types for loading operator and enemy archetypes.
+//!
+//! An archetype ("def") is everything that is the same between every instance
+//! of an operator or enemy: their base stats, how they attack, their range,
+//! skills and model. Units are spawned with a handle to their def, and are
+//! filled in once the def has loaded.
+
+use serde::Deserialize;
+
+use std::num::NonZeroU32;
+use std::time::Duration;
+
+use bevy::ecs::system::EntityCommands;
+use bevy::reflect::{TypeUuid, TypePath};
+use bevy::prelude::*;
+
+use crate::battle::auto_attack::{AttackCycle, AreaAttack, Chain, Melee, Splash};
+use crate::battle::blocking::Blockable;
+use crate::battle::damage::{DamageType, DespawnOnDeath, Health};
+use crate::battle::area::{Falloff, HitFilter};
+use crate::battle::path::Follower;
+use crate::battle::skill::{
+    AutoSkillActivation,
+    IncreaseWithTime,
+    OverflowBehavior,
+    Skill,
+    SkillBundle,
+    SkillDuration,
+};
+use crate::battle::targeting::{self, TargetPriority, Targeting, TargetingBundle};
+use crate::battle::{BoundingCircle, EnemyBundle, Hostility, OperatorBundle};
+use crate::stats::{stat, EnemyStatBundle, OperatorStatBundle, StatBundle};
+use crate::tile_map::nav::NavBundle;
+use crate::tile_map::range::{Facing, Range};
+use crate::tile_map::{Coordinates, DeployClass};
+
+use super::teardown::StageEntity;
+
+/// How long enemies stay around after dying.
+pub const ENEMY_DESPAWN_DELAY: Duration = Duration::from_millis(200);
+
+/// An enemy archetype.
+///
+/// Can be loaded from RON, with the extension `.enemy.ron`.
+#[derive(Debug, Clone, Deserialize, TypeUuid, TypePath)]
+#[uuid = "6f5b2d6e-2c1a-4a8e-9b0e-3f4c1d7a9e21"]
+pub struct EnemyDef {
+    /// The name of the enemy.
+    pub name: String,
+    /// Base stats. Any stats left out use the stat's default.
+    #[serde(default)]
+    pub stats: EnemyStats,
+    /// How the enemy attacks.
+    #[serde(default)]
+    pub attack: AttackDef,
+    /// The radius of the enemy's range, if it can attack without being
+    /// blocked.
+    #[serde(default)]
+    pub range: Option<f32>,
+    /// How much block the enemy takes up. See [`Blockable::cost`].
+    #[serde(default = "default_block_cost")]
+    pub block_cost: i32,
+    /// The radius of the enemy's [`BoundingCircle`].
+    #[serde(default = "default_enemy_radius")]
+    pub radius: f32,
+    /// Path to the enemy's model.
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+/// An operator archetype.
+///
+/// Can be loaded from RON, with the extension `.operator.ron`.
+#[derive(Debug, Clone, Deserialize, TypeUuid, TypePath)]
+#[uuid = "c3a4e0b7-5d2f-4f61-8a9c-0b1e2d3f4a58"]
+pub struct OperatorDef {
+    /// The name of the operator.
+    pub name: String,
+    /// Which tiles the operator can be deployed on.
+    #[serde(default)]
+    pub class: DeployClass,
+    /// Base stats. Any stats left out use the stat's default.
+    #[serde(default)]
+    pub stats: OperatorStats,
+    /// How the operator attacks.
+    #[serde(default)]
+    pub attack: AttackDef,
+    /// The tiles in range of the operator, when facing right.
+    #[serde(default)]
+    pub range: Vec<IVec2>,
+    /// The skills the operator can pick from.
+    #[serde(default)]
+    pub skills: Vec<SkillDef>,
+    /// The radius of the operator's [`BoundingCircle`].
+    #[serde(default = "default_operator_radius")]
+    pub radius: f32,
+    /// Path to the operator's model.
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+/// Base stats for an [`EnemyDef`].
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct EnemyStats {
+    pub hp: Option<i32>,
+    pub atk: Option<i32>,
+    pub def: Option<i32>,
+    pub res: Option<i32>,
+    pub atk_interval: Option<f32>,
+    pub aspd: Option<i32>,
+    pub move_speed: Option<f32>,
+    pub weight: Option<i32>,
+    pub mass: Option<f32>,
+}
+
+impl EnemyStats {
+    /// Creates the stat bundle for these stats.
+    pub fn bundle(&self) -> EnemyStatBundle {
+        EnemyStatBundle {
+            hp: stat_bundle(self.hp.map(stat::MaxHp::new)),
+            atk: stat_bundle(self.atk.map(stat::Atk::new)),
+            def: stat_bundle(self.def.map(stat::Def::new)),
+            res: stat_bundle(self.res.map(stat::Res::new)),
+            atk_interval: stat_bundle(self.atk_interval.map(stat::AtkInterval::new)),
+            aspd: stat_bundle(self.aspd.map(stat::Aspd::new)),
+            move_speed: stat_bundle(self.move_speed.map(stat::MoveSpeed::new)),
+            weight: stat_bundle(self.weight.map(stat::Weight::new)),
+            mass: stat_bundle(self.mass.map(stat::Mass::new)),
+        }
+    }
+}
+
+/// Base stats for an [`OperatorDef`].
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct OperatorStats {
+    pub hp: Option<i32>,
+    pub atk: Option<i32>,
+    pub def: Option<i32>,
+    pub res: Option<i32>,
+    pub atk_interval: Option<f32>,
+    pub aspd: Option<i32>,
+    pub redeploy_time: Option<f32>,
+    pub dp_cost: Option<i32>,
+    pub block: Option<i32>,
+}
+
+impl OperatorStats {
+    /// Creates the stat bundle for these stats.
+    pub fn bundle(&self) -> OperatorStatBundle {
+        OperatorStatBundle {
+            hp: stat_bundle(self.hp.map(stat::MaxHp::new)),
+            atk: stat_bundle(self.atk.map(stat::Atk::new)),
+            def: stat_bundle(self.def.map(stat::Def::new)),
+            res: stat_bundle(self.res.map(stat::Res::new)),
+            atk_interval: stat_bundle(self.atk_interval.map(stat::AtkInterval::new)),
+            aspd: stat_bundle(self.aspd.map(stat::Aspd::new)),
+            redeploy_time: stat_bundle(self.redeploy_time.map(stat::RedeployTime::new)),
+            dp_cost: stat_bundle(self.dp_cost.map(stat::DpCost::new)),
+            block: stat_bundle(self.block.map(stat::Block::new)),
+        }
+    }
+}
+
+/// How a unit attacks.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AttackDef {
+    /// The frontswing of the attack in seconds. See [`AttackCycle`].
+    pub frontswing: f32,
+    /// The backswing of the attack in seconds. See [`AttackCycle`].
+    pub backswing: f32,
+    pub damage_type: DamageType,
+    pub scheme: AttackScheme,
+    /// The maximum amount of targets.
+    pub max_targets: Option<usize>,
+    pub priority: TargetPriority,
+}
+
+impl AttackDef {
+    /// Creates the [`AttackCycle`] for the attack.
+    pub fn attack_cycle(&self) -> AttackCycle {
+        AttackCycle::new(
+            Duration::from_secs_f32(self.frontswing),
+            Duration::from_secs_f32(self.backswing),
+        )
+    }
+
+    /// Creates the [`Targeting`] for the attack.
+    pub fn targeting(&self) -> Targeting {
+        Targeting {
+            max_targets: self.max_targets.unwrap_or(1),
+            priority: self.priority,
+            ..default()
+        }
+    }
+
+    /// Inserts the attack cycle and attack scheme components on an entity.
+    pub fn insert(&self, commands: &mut EntityCommands) {
+        commands.insert((self.attack_cycle(), self.targeting()));
+
+        match self.scheme {
+            AttackScheme::Melee => {
+                commands.insert(Melee::default().with_type(self.damage_type));
+            }
+            AttackScheme::Splash { radius, falloff } => {
+                commands.insert(Splash::new(radius)
+                    .with_type(self.damage_type)
+                    .with_falloff(falloff));
+            }
+            AttackScheme::Area { falloff, filter } => {
+                commands.insert(AreaAttack::default()
+                    .with_type(self.damage_type)
+                    .with_falloff(falloff)
+                    .with_filter(filter));
+            }
+            AttackScheme::Chain { bounces, radius, decay } => {
+                commands.insert(Chain::new(bounces, radius, decay)
+                    .with_type(self.damage_type));
+            }
+        }
+    }
+}
+
+/// The attack scheme of a unit. See [`auto_attack`][1].
+///
+/// [1]: crate::battle::auto_attack
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub enum AttackScheme {
+    #[default]
+    Melee,
+    Splash {
+        radius: f32,
+        #[serde(default)]
+        falloff: Falloff,
+    },
+    Area {
+        #[serde(default)]
+        falloff: Falloff,
+        #[serde(default)]
+        filter: HitFilter,
+    },
+    Chain {
+        bounces: usize,
+        radius: f32,
+        decay: f32,
+    },
+}
+
+/// A skill an operator can bring.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SkillDef {
+    /// The name of the skill.
+    pub name: String,
+    /// The SP needed to activate the skill.
+    pub sp_cost: f32,
+    /// The SP the skill starts with.
+    #[serde(default)]
+    pub initial_sp: f32,
+    /// How long the skill lasts in seconds. Leave this out for burst skills.
+    #[serde(default)]
+    pub duration: Option<f32>,
+    /// How many charges the skill can store.
+    #[serde(default)]
+    pub charges: Option<NonZeroU32>,
+    /// Whether the skill activates on its own.
+    #[serde(default)]
+    pub auto: bool,
+    /// The tiles in range of the skill, when facing right. Leave this out to
+    /// use the operator's range.
+    #[serde(default)]
+    pub range: Option<Vec<IVec2>>,
+}
+
+impl SkillDef {
+    /// Creates the [`Skill`] for the skill.
+    pub fn skill(&self) -> Skill {
+        let overflow = match self.charges {
+            Some(charges) => OverflowBehavior::Charge(charges),
+            None => OverflowBehavior::Capped,
+        };
+
+        Skill::new(self.sp_cost, overflow)
+            .with_initial_sp(self.initial_sp)
+    }
+}
+
+fn default_block_cost() -> i32 {
+    1
+}
+
+fn default_enemy_radius() -> f32 {
+    0.15
+}
+
+fn default_operator_radius() -> f32 {
+    0.5
+}
+
+fn stat_bundle<T>(stat: Option<T>) -> StatBundle<T>
+where
+    T: crate::stats::Stat + Default,
+{
+    StatBundle::new(stat.unwrap_or_default())
+}
+
+fn range_tiles(tiles: &[IVec2]) -> Range {
+    Range::new(tiles
+        .iter()
+        .copied()
+        .map(Coordinates::from)
+        .collect::<Vec<_>>())
+}
+
+/// A bundle for spawning an enemy from an [`EnemyDef`].
+///
+/// The rest of the enemy is filled in once the def loads.
+#[derive(Bundle, Clone, Default)]
+pub struct EnemySpawnBundle {
+    pub def: Handle<EnemyDef>,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub visibility: Visibility,
+    pub computed_visibility: ComputedVisibility,
+    pub follower: Follower,
+    pub stage_entity: StageEntity,
+}
+
+/// A bundle for spawning an operator from an [`OperatorDef`].
+///
+/// The rest of the operator is filled in once the def loads. **Remember to
+/// parent this entity to the grid.**
+#[derive(Bundle, Clone, Default)]
+pub struct OperatorSpawnBundle {
+    pub def: Handle<OperatorDef>,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub visibility: Visibility,
+    pub computed_visibility: ComputedVisibility,
+    pub coordinates: Coordinates,
+    pub facing: Facing,
+    pub selected_skill: SelectedSkill,
+    pub stage_entity: StageEntity,
+}
+
+/// Which of the [`OperatorDef::skills`] the operator brings.
+#[derive(Clone, Copy, Component, Debug, Default)]
+pub struct SelectedSkill(pub Option<usize>);
+
+/// Fills in enemies spawned with an [`EnemySpawnBundle`].
+///
+/// Enemies that already have [`Health`] are considered spawned.
+pub fn spawn_enemies(
+    mut commands: Commands,
+    query: Query<(Entity, &Handle<EnemyDef>, &Transform, &Follower), Without<Health>>,
+    defs: Res<Assets<EnemyDef>>,
+    asset_server: Res<AssetServer>,
+) {
+    for (entity, handle, transform, follower) in query.iter() {
+        let Some(def) = defs.get(handle) else {
+            continue;
+        };
+
+        let stats = def.stats.bundle();
+        let health = Health::new(stats.hp.stat.get());
+
+        let mut entity_commands = commands.entity(entity);
+
+        entity_commands.insert((
+            EnemyBundle {
+                transform: *transform,
+                follower: follower.clone(),
+                stats,
+                health,
+                bounding_circle: BoundingCircle::new(def.radius),
+                blockable: Blockable::with_cost(def.block_cost),
+                ..default()
+            },
+            NavBundle::default(),
+            DespawnOnDeath::new(ENEMY_DESPAWN_DELAY),
+            Name::new(def.name.clone()),
+        ));
+
+        def.attack.insert(&mut entity_commands);
+
+        if let Some(radius) = def.range {
+            entity_commands.insert(targeting::Range::circle(radius));
+        }
+
+        if let Some(model) = &def.model {
+            entity_commands.with_children(|parent| {
+                parent.spawn(SceneBundle {
+                    scene: asset_server.load(model),
+                    ..default()
+                });
+            });
+        }
+    }
+}
+
+/// Fills in operators spawned with an [`OperatorSpawnBundle`].
+///
+/// Operators that already have [`Health`] are considered spawned.
+pub fn spawn_operators(
+    mut commands: Commands,
+    query: Query<(Entity, &Handle<OperatorDef>, &Coordinates, &SelectedSkill), Without<Health>>,
+    defs: Res<Assets<OperatorDef>>,
+    asset_server: Res<AssetServer>,
+) {
+    for (entity, handle, coordinates, selected_skill) in query.iter() {
+        let Some(def) = defs.get(handle) else {
+            continue;
+        };
+
+        let stats = def.stats.bundle();
+        let health = Health::new(stats.hp.stat.get());
+
+        let mut entity_commands = commands.entity(entity);
+
+        entity_commands.insert((
+            OperatorBundle {
+                coordinates: *coordinates,
+                stats,
+                health,
+                bounding_circle: BoundingCircle::new(def.radius),
+                ..default()
+            },
+            range_tiles(&def.range),
+            def.class,
+            Name::new(def.name.clone()),
+        ));
+
+        def.attack.insert(&mut entity_commands);
+
+        entity_commands.with_children(|parent| {
+            if let Some(skill) = selected_skill.0.and_then(|idx| def.skills.get(idx)) {
+                let range = skill.range.as_deref().unwrap_or(&def.range);
+
+                let mut skill_commands = parent.spawn((
+                    Name::new(skill.name.clone()),
+                    SpatialBundle::default(),
+                    SkillBundle {
+                        skill: skill.skill(),
+                        ..default()
+                    },
+                    IncreaseWithTime,
+                    TargetingBundle::default(),
+                    Hostility::Friendly,
+                    range_tiles(range),
+                ));
+
+                if let Some(duration) = skill.duration {
+                    skill_commands.insert(SkillDuration(Duration::from_secs_f32(duration)));
+                }
+
+                if skill.auto {
+                    skill_commands.insert(AutoSkillActivation::one());
+                }
+            }
+
+            if let Some(model) = &def.model {
+                parent.spawn(SceneBundle {
+                    scene: asset_server.load(model),
+                    ..default()
+                });
+            }
+        });
+    }
+}
@@ -1,23 +1,11 @@
 use bevy::prelude::*;
 
-use std::time::Duration;
-
 use spcc::AppState;
 
-use spcc::loader::{LoadStageEvent, StageBuilder};
-use spcc::battle::{
-    path::{Checkpoint, Follower},
-    auto_attack::{AttackCycle, Melee},
-    targeting::{TargetingBundle, Range},
-    skill::{Skill, SkillBundle, OverflowBehavior, IncreaseWithTime, AutoSkillActivation},
-    Hostility,
-    EnemyBundle,
-    EnemyStatBundle,
-    OperatorBundle,
-    OperatorStatBundle,
-    StatBundle,
-};
-use spcc::tile_map::nav::NavBundle;
+use spcc::loader::{LoadStageEvent, SquadMember, StageAssets, StageBuilder};
+use spcc::loader::unit::{EnemySpawnBundle, OperatorSpawnBundle, SelectedSkill};
+use spcc::battle::path::{Checkpoint, Follower};
+use spcc::tile_map::range::{Direction, Facing};
 use spcc::tile_map::{Coordinates, Grid};
 use spcc::stats::{Stat as _, stat};
 //use spcc::effect::HpDecay;
@@ -34,17 +22,20 @@ fn main() {
             //DefaultPickingPlugins,
             #[cfg(feature = "debug")]
             WorldInspectorPlugin::new(),
+            #[cfg(feature = "debug")]
+            spcc::editor::EditorPlugin,
             spcc::loader::LoaderPlugin,
             spcc::battle::BattlePlugins,
             spcc::stats::StatPlugin,
             spcc::tile_map::GridPlugin,
             spcc::tile_map::nav::NavPlugin,
+            spcc::tile_map::effect::TileEffectPlugin,
             spcc::material::MaterialPlugin,
             spcc::status::StatusPlugin,
             spcc::ui::UiPlugin,
+            spcc::tile_map::focus::FocusPlugin,
             // DEBUG:
             spcc::battle::DebugDrawPlugin,
-            //spcc::tile_map::focus::FocusPlugin,
         ))
         .add_state::<AppState>()
         .add_systems(Startup, setup)
@@ -53,67 +44,35 @@ fn main() {
 }
 
 pub fn setup_tile_map(
-    mut debounce: Local<bool>,
     mut commands: Commands,
-    query: Query<Entity, With<Grid>>,
+    query: Query<Entity, Added<Grid>>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    stage_assets: Res<StageAssets>,
 ) {
-    if *debounce {
+    let Ok(grid) = query.get_single() else {
         return;
-    }
+    };
 
-    let Ok(grid) = query.get_single() else {
+    let Some(squad) = stage_assets.squad().first() else {
         return;
     };
 
-    *debounce = true;
+    let Some(enemy) = stage_assets.enemies().first() else {
+        return;
+    };
 
     // FIXME: test operator
     commands
-        .spawn((
-            OperatorBundle {
-                coordinates: Coordinates::new(6, 5),
-                stats: OperatorStatBundle {
-                    hp: StatBundle::new(stat::MaxHp::new(2200)),
-                    def: StatBundle::new(stat::Def::new(280)),
-                    atk_interval: StatBundle::new(stat::AtkInterval::new(0.5)),
-                    ..default()
-                },
-                ..default()
-            },
-            AttackCycle::new(Duration::from_millis(200), Duration::from_millis(150)),
-            Melee::default(),
-            Range::from_vertices([
-                Vec2::new(1.5, -0.5),
-                Vec2::new(1.5, 0.5),
-                Vec2::new(-0.5, 0.5),
-                Vec2::new(-0.5, -0.5),
-            ]),
-        ))
+        .spawn(OperatorSpawnBundle {
+            def: squad.def.clone(),
+            coordinates: Coordinates::new(6, 5),
+            facing: Facing(Direction::Right),
+            selected_skill: SelectedSkill(squad.skill),
+            ..default()
+        })
         .set_parent(grid)
         .with_children(|parent| {
-            // skill
-            parent
-                .spawn((
-                    SpatialBundle::default(),
-                    SkillBundle {
-                        skill: Skill::new(4.0, OverflowBehavior::Capped)
-                            .with_initial_sp(2.0),
-                        ..default()
-                    },
-                    AutoSkillActivation::one(),
-                    IncreaseWithTime,
-                    TargetingBundle::default(),
-                    Hostility::Friendly,
-                    Range::from_vertices([
-                        Vec2::new(1.5, -0.5),
-                        Vec2::new(1.5, 0.5),
-                        Vec2::new(-0.5, 0.5),
-                        Vec2::new(-0.5, -0.5),
-                    ]),
-                ));
-
             // model
             parent
                 .spawn(PbrBundle {
@@ -136,34 +95,21 @@ pub fn setup_tile_map(
 
     // FIXME: test enemy
     commands
-        .spawn((
-            EnemyBundle {
-                transform: Transform::from_xyz(6.0, 0.0, 0.0),
-                follower: Follower::new([
-                    Checkpoint::at(Vec2::new(-6.0, 0.0)),
-                    Checkpoint::at(Vec2::new(6.0, 3.0)),
-                    Checkpoint::at(Vec2::new(-6.0, 3.0)),
-                    Checkpoint::at(Vec2::new(-5.0, -3.0)),
-                    Checkpoint::at(Vec2::new(6.0, 0.0)),
-                    Checkpoint::at(Vec2::new(0.0, 3.0)),
-                    Checkpoint::at(Vec2::new(0.0, 0.0)),
-                    Checkpoint::at(Vec2::new(6.0, 3.0)),
-                ]),
-                stats: EnemyStatBundle {
-                    hp: StatBundle::new(stat::MaxHp::new(4000)),
-                    atk: StatBundle::new(stat::Atk::new(420)),
-                    def: StatBundle::new(stat::Def::new(80)),
-                    res: StatBundle::new(stat::Res::new(0)),
-                    ..default()
-                },
-                hatred: spcc::battle::targeting::Hatred(1),
-                ..default()
-            },
-            AttackCycle::new(Duration::from_millis(200), Duration::from_millis(150)),
-            Melee::default(),
-            NavBundle::default(),
-            spcc::battle::damage::DespawnOnDeath::new(Duration::from_millis(200)),
-        ))
+        .spawn(EnemySpawnBundle {
+            def: enemy.clone(),
+            transform: Transform::from_xyz(6.0, 0.0, 0.0),
+            follower: Follower::new([
+                Checkpoint::at(Vec2::new(-6.0, 0.0)),
+                Checkpoint::at(Vec2::new(6.0, 3.0)),
+                Checkpoint::at(Vec2::new(-6.0, 3.0)),
+                Checkpoint::at(Vec2::new(-5.0, -3.0)),
+                Checkpoint::at(Vec2::new(6.0, 0.0)),
+                Checkpoint::at(Vec2::new(0.0, 3.0)),
+                Checkpoint::at(Vec2::new(0.0, 0.0)),
+                Checkpoint::at(Vec2::new(6.0, 3.0)),
+            ]),
+            ..default()
+        })
         .with_children(|parent| {
             parent
                 .spawn(PbrBundle {
@@ -207,5 +153,10 @@ pub fn setup(
     ));
 
     // begin stage loading
-    stage_load_tx.send(StageBuilder::new("maps/ccmap.ron").into());
+    stage_load_tx.send(
+        StageBuilder::new("maps/ccmap.ron")
+            .with_operator(SquadMember::new("operators/test_operator.operator.ron").with_skill(0))
+            .with_enemy("enemies/test_enemy.enemy.ron")
+            .into()
+    );
 }
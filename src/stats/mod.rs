@@ -24,7 +24,9 @@ impl Plugin for StatPlugin {
             .add_stat::<stat::MoveSpeed>()
             .add_stat::<stat::RedeployTime>()
             .add_stat::<stat::DpCost>()
-            .add_stat::<stat::Block>();
+            .add_stat::<stat::Block>()
+            .add_stat::<stat::Weight>()
+            .add_stat::<stat::Mass>();
     }
 }
 
@@ -38,6 +40,8 @@ pub struct EnemyStatBundle {
     pub atk_interval: StatBundle<stat::AtkInterval>,
     pub aspd: StatBundle<stat::Aspd>,
     pub move_speed: StatBundle<stat::MoveSpeed>,
+    pub weight: StatBundle<stat::Weight>,
+    pub mass: StatBundle<stat::Mass>,
 }
 
 /// A bundle for operator stats.
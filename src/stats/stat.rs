@@ -220,6 +220,32 @@ impl Default for Block {
     }
 }
 
+/// **Enemy only** Weight class, which resists forced movement.
+///
+/// Forces stronger than the weight move the entity farther. See
+/// [`Force::distance_against`][1].
+///
+/// [1]: crate::battle::displacement::Force::distance_against
+#[derive(Clone, Component, Debug, PartialEq)]
+pub struct Weight(i32);
+
+impl Default for Weight {
+    fn default() -> Weight {
+        Weight(1)
+    }
+}
+
+/// **Enemy only** Mass, which determines how fast an entity is moved by
+/// forced movement. Heavier entities are moved slower.
+#[derive(Clone, Component, Debug, PartialEq)]
+pub struct Mass(f32);
+
+impl Default for Mass {
+    fn default() -> Mass {
+        Mass(1.0)
+    }
+}
+
 // maxhp must be at least one, to make division math easier in other structs,
 // since hp is typically represented as percentage of max
 impl_stat_i32!(MaxHp, min: 1);
@@ -233,4 +259,6 @@ impl_stat_f32!(MoveSpeed, min: 0.0);
 impl_stat_f32!(RedeployTime, min: 0.0);
 impl_stat_i32!(DpCost, min: 0);
 impl_stat_i32!(Block, min: 0);
+impl_stat_i32!(Weight, min: 0);
+impl_stat_f32!(Mass, min: 0.1);
 
@@ -21,6 +21,24 @@ impl Plugin for StatusPlugin {
     }
 }
 
+/// A status effect, as shown to the player.
+///
+/// Put this on the same entity as the components that make up the effect, so
+/// the effect can be listed on the unit it applies to.
+#[derive(Clone, Component, Debug)]
+pub struct StatusEffect {
+    pub name: String,
+}
+
+impl StatusEffect {
+    /// Creates a new `StatusEffect`.
+    pub fn new(name: impl Into<String>) -> StatusEffect {
+        StatusEffect {
+            name: name.into(),
+        }
+    }
+}
+
 /// The activated originium buff.
 ///
 /// Can be added to an entity as a bundle, but you should add this bundle as
@@ -31,11 +49,13 @@ pub struct ActivatedOriginiumStatus {
     atk_buff: stat::AtkModifier,
     aspd_buff: stat::AspdModifier,
     hp_decay: HpDecay,
+    status_effect: StatusEffect,
 }
 
 impl Default for ActivatedOriginiumStatus {
     fn default() -> ActivatedOriginiumStatus {
         ActivatedOriginiumStatus {
+            status_effect: StatusEffect::new("Activated Originium"),
             atk_buff: stat::AtkModifier::identity().add(600),
             aspd_buff: stat::AspdModifier::identity().add(50),
             hp_decay: HpDecay::new(150.0),
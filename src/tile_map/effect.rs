@@ -0,0 +1,254 @@
+//! Tile effects, applied to units standing on tiles.
+
+use super::*;
+
+use crate::battle::damage::{DamageType, DamageReceivedEvent, DamageSystems, Dead, Health};
+use crate::stats::{stat, Stat as _};
+
+use bevy::prelude::*;
+
+pub struct TileEffectPlugin;
+
+impl Plugin for TileEffectPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(
+                Update,
+                (
+                    update_standing_on,
+                    (
+                        fall_into_holes,
+                        apply_tile_damage,
+                        apply_tile_slow,
+                    ),
+                )
+                    .chain()
+                    .before(DamageSystems::AccumulateDamage),
+            );
+    }
+}
+
+/// The tile a unit is currently standing on.
+///
+/// This is inserted automatically on every entity with [`Health`].
+#[derive(Clone, Component, Debug)]
+pub struct StandingOn {
+    coordinates: Option<Coordinates>,
+    modifier: Entity,
+}
+
+impl StandingOn {
+    /// The coordinates of the tile. `None` if the unit is not over any tile.
+    pub fn coordinates(&self) -> Option<Coordinates> {
+        self.coordinates
+    }
+}
+
+/// Marker for the modifier entity that [`TileEffect::Slow`] is applied
+/// through.
+#[derive(Clone, Component, Debug, Default)]
+pub struct TileEffectModifier;
+
+pub fn update_standing_on(
+    mut commands: Commands,
+    mut query: Query<(Entity, &GlobalTransform, Option<&Coordinates>, Option<&mut StandingOn>), With<Health>>,
+    grid_query: Query<&GlobalTransform, With<Grid>>,
+) {
+    let Ok(grid_transform) = grid_query.get_single() else {
+        return;
+    };
+
+    for (entity, transform, coordinates, standing_on) in query.iter_mut() {
+        // gridlocked entities already know where they are
+        let coordinates = coordinates.copied().unwrap_or_else(|| {
+            let local = grid_transform
+                .affine()
+                .inverse()
+                .transform_point(transform.translation());
+
+            Coordinates::nearest(local)
+        });
+
+        match standing_on {
+            Some(mut standing_on) => {
+                if standing_on.coordinates != Some(coordinates) {
+                    standing_on.coordinates = Some(coordinates);
+                }
+            }
+            None => {
+                let modifier = commands
+                    .spawn((
+                        TileEffectModifier,
+                        stat::MoveSpeed::modif(),
+                    ))
+                    .set_parent(entity)
+                    .id();
+
+                commands
+                    .entity(entity)
+                    .insert(StandingOn {
+                        coordinates: Some(coordinates),
+                        modifier,
+                    });
+            }
+        }
+    }
+}
+
+pub fn fall_into_holes(
+    mut query: Query<(&StandingOn, &mut Health), (Changed<StandingOn>, Without<Dead>)>,
+    grid_query: Query<&Grid>,
+) {
+    let Ok(grid) = grid_query.get_single() else {
+        return;
+    };
+
+    for (standing_on, mut health) in query.iter_mut() {
+        let Some(tile) = standing_on.coordinates.and_then(|c| grid.get(&c)) else {
+            continue;
+        };
+
+        if tile.is_fatal() {
+            health.set(0.0);
+        }
+    }
+}
+
+pub fn apply_tile_damage(
+    mut query: Query<(Entity, &StandingOn, &mut Health), Without<Dead>>,
+    grid_query: Query<&Grid>,
+    mut damage_received_tx: EventWriter<DamageReceivedEvent>,
+    time: Res<Time>,
+) {
+    let Ok(grid) = grid_query.get_single() else {
+        return;
+    };
+
+    for (entity, standing_on, mut health) in query.iter_mut() {
+        let Some(tile) = standing_on.coordinates.and_then(|c| grid.get(&c)) else {
+            continue;
+        };
+
+        for effect in tile.effects() {
+            match effect {
+                TileEffect::Heal(hp) => {
+                    let current_hp = health.get();
+                    health.set(current_hp + hp * time.delta_seconds());
+                }
+                TileEffect::Damage(hp) => {
+                    damage_received_tx.send(DamageReceivedEvent::new(entity)
+                        .with_type(DamageType::True)
+                        .with_damage(hp * time.delta_seconds()));
+                }
+                TileEffect::Slow(_) => (),
+            }
+        }
+    }
+}
+
+pub fn apply_tile_slow(
+    mut modifier_query: Query<(Ref<TileEffectModifier>, &Parent, &mut stat::MoveSpeedModifier)>,
+    standing_on_query: Query<Ref<StandingOn>>,
+    grid_query: Query<&Grid>,
+) {
+    let Ok(grid) = grid_query.get_single() else {
+        return;
+    };
+
+    for (marker, parent, mut modifier) in modifier_query.iter_mut() {
+        let Ok(standing_on) = standing_on_query.get(parent.get()) else {
+            continue;
+        };
+
+        // the modifier is spawned in the same frame the unit first stands on
+        // a tile, so it can miss that change
+        if !standing_on.is_changed() && !marker.is_added() {
+            continue;
+        }
+
+        let slow = standing_on.coordinates
+            .and_then(|c| grid.get(&c))
+            .map(|tile| {
+                tile
+                    .effects()
+                    .iter()
+                    .map(|effect| match effect {
+                        TileEffect::Slow(slow) => *slow,
+                        _ => 0.0,
+                    })
+                    .sum::<f32>()
+            })
+            .unwrap_or_default();
+
+        *modifier = stat::MoveSpeed::modif().mul(-slow);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::stats::{Modifier, Stat};
+
+    #[test]
+    fn slow_applies_to_unit_spawned_on_tile() {
+        let mut app = App::new();
+
+        app.add_systems(Update, (update_standing_on, apply_tile_slow).chain());
+
+        let tile = Tile::new(TileKind::Ground, false)
+            .with_effects([TileEffect::Slow(0.4)]);
+
+        app.world.spawn((
+            Grid::from_tiles([(Coordinates::new(0, 0), tile)]),
+            GlobalTransform::default(),
+        ));
+        app.world.spawn((Health::default(), GlobalTransform::default()));
+
+        // the unit stays on the tile
+        app.update();
+        app.update();
+
+        let modifier = app.world
+            .query_filtered::<&stat::MoveSpeedModifier, With<TileEffectModifier>>()
+            .single(&app.world)
+            .clone();
+
+        let mut final_mod = stat::MoveSpeedModifier::base();
+        final_mod.combine(&modifier);
+
+        let mut move_speed = stat::MoveSpeed::new(1.0);
+        move_speed.apply(&final_mod);
+
+        assert!((move_speed.get() - 0.6).abs() < 1e-5);
+    }
+
+    #[test]
+    fn standing_on_nearest_tile() {
+        let mut app = App::new();
+
+        app.add_systems(Update, update_standing_on);
+
+        app.world.spawn((
+            Grid::from_tiles([
+                (Coordinates::new(2, 0), Tile::new(TileKind::Ground, false)),
+                (Coordinates::new(3, 0), Tile::new(TileKind::Ground, false)),
+            ]),
+            GlobalTransform::default(),
+        ));
+
+        // just past the left edge of tile 3
+        let unit = app.world
+            .spawn((
+                Health::default(),
+                GlobalTransform::from_translation(Vec3::new(2.6, 0.0, 0.0)),
+            ))
+            .id();
+
+        app.update();
+
+        let standing_on = app.world.get::<StandingOn>(unit).unwrap();
+
+        assert_eq!(standing_on.coordinates(), Some(Coordinates::new(3, 0)));
+    }
+}
@@ -1,23 +1,35 @@
-//! Focus entities that are grid-locked, using [`bevy_mod_picking`].
+//! Picking tiles and units with the cursor, and focusing grid-locked units.
+//!
+//! Picking is done by casting a ray from the cursor against the plane of the
+//! grid, so it does not depend on any meshes.
 
 use super::*;
-use super::range::Range;
+use super::highlight::HighlightDanger;
+use super::range::{HighlightRange, Range};
 
 use bevy::prelude::*;
+use bevy::ecs::system::SystemParam;
+use bevy::math::swizzles::*;
+use bevy::window::PrimaryWindow;
 
-use bevy_mod_picking::prelude::*;
+use crate::battle::{BoundingCircle, Hostility};
+use crate::battle::damage::Dead;
+use crate::battle::path::Hidden;
 
 pub struct FocusPlugin;
 
 impl Plugin for FocusPlugin {
     fn build(&self, app: &mut App) {
         app
+            .init_resource::<PointerHover>()
+            .add_event::<PointerClickEvent>()
             .add_systems(
-                PostUpdate,
+                Update,
                 (
-                    allow_focus_on_tiles,
+                    update_pointer,
+                    change_focus,
                     highlight_range_focus,
-                )
+                ).chain(),
             );
     }
 }
@@ -26,70 +38,203 @@ impl Plugin for FocusPlugin {
 #[derive(Clone, Component, Debug, Default)]
 pub struct Focus;
 
-pub fn allow_focus_on_tiles(
-    mut commands: Commands,
-    query: Query<Entity, Added<Tile>>,
-) {
-    for entity in query.iter() {
-        commands
-            .entity(entity)
-            .insert((
-                RaycastPickTarget::default(),
-                On::<Pointer<Click>>::run(change_focus),
-            ));
+/// A component marking the tile or unit under the cursor.
+#[derive(Clone, Component, Debug, Default)]
+pub struct Hovered;
+
+/// What is under the cursor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pick {
+    /// The grid the cursor is over.
+    pub grid: Entity,
+    /// The coordinates the cursor is over. There may not be a tile here.
+    pub coordinates: Coordinates,
+    /// The tile at [`Pick::coordinates`].
+    pub tile: Option<Entity>,
+    /// The unit under the cursor.
+    pub unit: Option<Entity>,
+}
+
+impl Pick {
+    /// The entity that was picked, preferring units over tiles.
+    pub fn entity(&self) -> Option<Entity> {
+        self.unit.or(self.tile)
     }
 }
 
-pub fn highlight_range_focus(
-    query: Query<(Entity, &Range, &Coordinates), Added<Focus>>,
-    mut tile_query: Query<&mut Handle<TileHighlightMaterial>>,
-    parents_query: Query<&Parent>,
-    grid_query: Query<&Grid>,
-    grid_assets: Res<GridAssets>,
+/// What is currently under the cursor, if anything.
+#[derive(Clone, Debug, Default, Resource)]
+pub struct PointerHover(pub Option<Pick>);
+
+/// Fires when a tile or unit is clicked.
+#[derive(Clone, Debug, Event)]
+pub struct PointerClickEvent {
+    pub pick: Pick,
+    pub button: MouseButton,
+}
+
+/// A system param for finding the tile under the cursor.
+#[derive(SystemParam)]
+pub struct CursorTile<'w, 's> {
+    windows: Query<'w, 's, &'static Window, With<PrimaryWindow>>,
+    cameras: Query<'w, 's, (&'static Camera, &'static GlobalTransform)>,
+    grids: Query<'w, 's, (Entity, &'static Grid, &'static GlobalTransform)>,
+}
+
+impl<'w, 's> CursorTile<'w, 's> {
+    /// The grid and coordinates under the cursor, if there are any.
+    pub fn get(&self) -> Option<(&Grid, Coordinates)> {
+        self.hit().map(|(_, grid, coordinates, _)| (grid, coordinates))
+    }
+
+    /// The grid and coordinates under the cursor, along with the world
+    /// position where the cursor meets the grid.
+    pub fn hit(&self) -> Option<(Entity, &Grid, Coordinates, Vec3)> {
+        let cursor = self.windows.get_single().ok()?.cursor_position()?;
+        let (camera, camera_transform) = self.cameras.iter().find(|(c, _)| c.is_active)?;
+        let ray = camera.viewport_to_world(camera_transform, cursor)?;
+
+        let (entity, grid, grid_transform) = self.grids.get_single().ok()?;
+
+        // the grid lies on its local XY plane
+        let normal = grid_transform.affine().transform_vector3(Vec3::Z);
+        let distance = ray.intersect_plane(grid_transform.translation(), normal)?;
+        let point = ray.get_point(distance);
+        let local = grid_transform
+            .affine()
+            .inverse()
+            .transform_point3(point);
+
+        Some((entity, grid, Coordinates::nearest(local), point))
+    }
+}
+
+pub fn update_pointer(
+    mut commands: Commands,
+    cursor: CursorTile,
+    unit_query: Query<(Entity, &GlobalTransform, &BoundingCircle), (Without<Dead>, Without<Hidden>)>,
+    gridlocked_query: Query<(Entity, &Coordinates, &Parent), Without<Tile>>,
+    hovered_query: Query<Entity, With<Hovered>>,
+    mouse: Res<Input<MouseButton>>,
+    mut hover: ResMut<PointerHover>,
+    mut click_tx: EventWriter<PointerClickEvent>,
 ) {
-    for (entity, range, coordinates) in query.iter() {
-        // highlight all tiles in range
-        for parent in parents_query.iter_ancestors(entity) {
-            let Ok(grid) = grid_query.get(parent) else {
-                continue;
-            };
-
-            // access tiles
-            for tile in range.tiles() {
-                let final_tile = *coordinates + *tile;
-
-                let Some(tile) = grid.lookup.get(&final_tile) else {
-                    continue;
-                };
-
-                if let Ok(mut material) = tile_query.get_mut(tile.entity) {
-                    // TODO: colors
-                    *material = grid_assets.hostile_indicator.clone();
-                }
+    let pick = cursor.hit().map(|(grid_entity, grid, coordinates, point)| {
+        let tile = grid.get(&coordinates).map(|tile| tile.entity);
+
+        // units that are under the cursor, closest first. gridlocked units
+        // can also be picked by their tile.
+        let unit = unit_query
+            .iter()
+            .filter_map(|(entity, transform, bounding_circle)| {
+                let distance = transform.translation().xy().distance(point.xy());
+
+                (distance <= bounding_circle.radius).then_some((entity, distance))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(entity, _)| entity)
+            .or_else(|| {
+                gridlocked_query
+                    .iter()
+                    .find(|(_, c, parent)| **c == coordinates && parent.get() == grid_entity)
+                    .map(|(entity, _, _)| entity)
+            });
+
+        Pick {
+            grid: grid_entity,
+            coordinates,
+            tile,
+            unit,
+        }
+    });
+
+    // move hover marker
+    let hovered = pick.and_then(|pick| pick.entity());
+
+    for entity in hovered_query.iter() {
+        if Some(entity) != hovered {
+            commands.entity(entity).remove::<Hovered>();
+        }
+    }
+
+    if let Some(entity) = hovered {
+        if !hovered_query.contains(entity) {
+            commands.entity(entity).insert(Hovered);
+        }
+    }
+
+    if hover.0 != pick {
+        hover.0 = pick;
+    }
+
+    // send clicks
+    if let Some(pick) = pick {
+        for button in [MouseButton::Left, MouseButton::Right] {
+            if mouse.just_pressed(button) {
+                click_tx.send(PointerClickEvent { pick, button });
             }
         }
     }
 }
 
+/// Focuses units that are left clicked. Clicking anything else, or the
+/// focused unit again, clears the focus.
 pub fn change_focus(
     mut commands: Commands,
-    listener: Listener<Pointer<Click>>,
-    grid_query: Query<Entity, With<Grid>>,
-    tile_coordinates_query: Query<(&Coordinates, &Parent), With<Tile>>,
-    coordinates_query: Query<(Entity, &Coordinates, &Parent), Without<Tile>>,
+    mut click_rx: EventReader<PointerClickEvent>,
+    focus_query: Query<Entity, With<Focus>>,
+) {
+    let Some(click) = click_rx
+        .iter()
+        .filter(|click| click.button == MouseButton::Left)
+        .last()
+    else {
+        return;
+    };
+
+    let mut refocus = click.pick.unit;
+
+    for entity in focus_query.iter() {
+        if Some(entity) == refocus {
+            refocus = None;
+        }
+
+        commands.entity(entity).remove::<Focus>();
+    }
+
+    if let Some(entity) = refocus {
+        commands.entity(entity).insert(Focus);
+    }
+}
+
+/// Highlights the range of focused operators, or the danger zone of focused
+/// enemies, and clears it once they are unfocused.
+pub fn highlight_range_focus(
+    mut commands: Commands,
+    query: Query<(Entity, Option<&Range>, Option<&Hostility>), Added<Focus>>,
+    mut unfocused: RemovedComponents<Focus>,
+    focus_query: Query<(), With<Focus>>,
 ) {
-    let tile = listener.target();
+    for entity in unfocused.iter() {
+        // may have been refocused in the same frame
+        if focus_query.contains(entity) {
+            continue;
+        }
 
-    // make sure our actions are constrained within the grid
-    let (tile_coords, parent) = tile_coordinates_query.get(tile).unwrap();
-    let grid = grid_query.get(parent.get()).unwrap();
+        if let Some(mut entity) = commands.get_entity(entity) {
+            entity.remove::<(HighlightRange, HighlightDanger)>();
+        }
+    }
 
-    // find entity with coordinates under parent that isn't tile
-    for (entity, coords, parent) in coordinates_query.iter() {
-        if parent.get() == grid && coords == tile_coords {
+    for (entity, range, hostility) in query.iter() {
+        if range.is_some() {
+            commands
+                .entity(entity)
+                .insert(HighlightRange::default());
+        } else if matches!(hostility, Some(Hostility::Hostile)) {
             commands
                 .entity(entity)
-                .insert(Focus);
+                .insert(HighlightDanger);
         }
     }
 }
@@ -0,0 +1,231 @@
+//! Tile highlighting.
+//!
+//! Anything that wants to color tiles puts a [`TileHighlight`] on an entity.
+//! Every frame, the highlights are composited so each tile shows the
+//! highest-priority [`HighlightLayer`] requested for it, and tiles that
+//! nothing requests go back to the default indicator. Removing the
+//! component, or despawning its entity, removes the highlight.
+
+use super::*;
+use super::range::{HighlightRange, Range as GridRange};
+
+use bevy::prelude::*;
+
+use std::collections::HashMap;
+
+use crate::find_parent;
+use crate::battle::{BoundingCircle, Hostility};
+use crate::battle::damage::Dead;
+use crate::battle::targeting::Range;
+
+/// A highlight layer.
+///
+/// Layers are ordered by priority, so later layers are drawn over earlier
+/// ones.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Reflect)]
+pub enum HighlightLayer {
+    /// Tiles that enemies can attack.
+    Danger,
+    /// Tiles an operator can attack.
+    #[default]
+    AttackRange,
+    /// Tiles an operator can be deployed on.
+    DeployPreview,
+}
+
+/// A request to highlight tiles.
+///
+/// The tiles are on the grid the entity is parented to, or on every grid if
+/// the entity isn't on a grid.
+#[derive(Clone, Component, Debug, Default)]
+pub struct TileHighlight {
+    pub layer: HighlightLayer,
+    pub tiles: Vec<Coordinates>,
+}
+
+impl TileHighlight {
+    /// Creates a new `TileHighlight`.
+    pub fn new(layer: HighlightLayer, tiles: impl Into<Vec<Coordinates>>) -> TileHighlight {
+        TileHighlight {
+            layer,
+            tiles: tiles.into(),
+        }
+    }
+}
+
+/// Highlights every tile an operator of a [`DeployClass`] can be deployed
+/// on, while it is on an entity.
+#[derive(Clone, Component, Debug, Default)]
+pub struct DeployPreview(pub DeployClass);
+
+/// Marks hostile entities whose [`Range`] is shown as a danger zone.
+#[derive(Clone, Component, Debug, Default)]
+pub struct HighlightDanger;
+
+/// Keeps the [`TileHighlight`] of every entity marked [`HighlightRange`] in
+/// sync with its range.
+pub fn highlight_ranges(
+    mut commands: Commands,
+    mut query: Query<(Entity, &GridRange, &Coordinates, &HighlightRange, Option<&mut TileHighlight>)>,
+    mut removed: RemovedComponents<HighlightRange>,
+) {
+    for entity in removed.iter() {
+        if let Some(mut entity) = commands.get_entity(entity) {
+            entity.remove::<TileHighlight>();
+        }
+    }
+
+    for (entity, range, coordinates, highlight_range, highlight) in query.iter_mut() {
+        let tiles = range
+            .tiles()
+            .iter()
+            .map(|tile| *coordinates + *tile)
+            .collect::<Vec<_>>();
+
+        match highlight {
+            Some(mut highlight) => {
+                if highlight.layer != highlight_range.0 || highlight.tiles != tiles {
+                    *highlight = TileHighlight::new(highlight_range.0, tiles);
+                }
+            }
+            None => {
+                commands
+                    .entity(entity)
+                    .insert(TileHighlight::new(highlight_range.0, tiles));
+            }
+        }
+    }
+}
+
+/// Keeps the [`TileHighlight`] of every [`DeployPreview`] in sync with the
+/// deployable tiles of the grid.
+pub fn highlight_deploy_previews(
+    mut commands: Commands,
+    query: Query<(Entity, Ref<DeployPreview>)>,
+    mut removed: RemovedComponents<DeployPreview>,
+    grid_query: Query<Ref<Grid>>,
+) {
+    for entity in removed.iter() {
+        if let Some(mut entity) = commands.get_entity(entity) {
+            entity.remove::<TileHighlight>();
+        }
+    }
+
+    let grid_changed = grid_query.iter().any(|grid| grid.is_changed());
+
+    for (entity, preview) in query.iter() {
+        if !preview.is_changed() && !grid_changed {
+            continue;
+        }
+
+        let tiles = grid_query
+            .iter()
+            .flat_map(|grid| grid.into_inner().iter())
+            .filter(|(_, tile)| tile.deployable_for(preview.0))
+            .map(|(coordinates, _)| *coordinates)
+            .collect::<Vec<_>>();
+
+        commands
+            .entity(entity)
+            .insert(TileHighlight::new(HighlightLayer::DeployPreview, tiles));
+    }
+}
+
+/// Keeps the [`TileHighlight`] of every entity marked [`HighlightDanger`] in
+/// sync with the tiles its [`Range`] covers.
+pub fn highlight_danger(
+    mut commands: Commands,
+    mut query: Query<(
+        Entity,
+        Ref<HighlightDanger>,
+        Ref<Range>,
+        Ref<GlobalTransform>,
+        Option<&Hostility>,
+        Option<&mut TileHighlight>,
+    ), Without<Dead>>,
+    mut removed: RemovedComponents<HighlightDanger>,
+    dead_query: Query<Entity, (With<HighlightDanger>, Added<Dead>)>,
+    tile_query: Query<(&Coordinates, &GlobalTransform), With<Tile>>,
+) {
+    for entity in removed.iter().chain(dead_query.iter()) {
+        if let Some(mut entity) = commands.get_entity(entity) {
+            entity.remove::<TileHighlight>();
+        }
+    }
+
+    let tile_center = BoundingCircle::new(0.0);
+
+    for (entity, marker, range, transform, hostility, highlight) in query.iter_mut() {
+        if !matches!(hostility, Some(Hostility::Hostile)) {
+            continue;
+        }
+
+        if !marker.is_added() && !range.is_changed() && !transform.is_changed() {
+            continue;
+        }
+
+        let (min, max) = range.bounds(&transform);
+
+        let tiles = tile_query
+            .iter()
+            .filter(|(_, tile_transform)| {
+                let pos = tile_transform.translation().truncate();
+
+                pos.cmpge(min).all()
+                    && pos.cmple(max).all()
+                    && range.intersects(&transform, tile_transform, &tile_center)
+            })
+            .map(|(coordinates, _)| *coordinates)
+            .collect::<Vec<_>>();
+
+        match highlight {
+            Some(mut highlight) => {
+                if highlight.tiles != tiles {
+                    highlight.tiles = tiles;
+                }
+            }
+            None => {
+                commands
+                    .entity(entity)
+                    .insert(TileHighlight::new(HighlightLayer::Danger, tiles));
+            }
+        }
+    }
+}
+
+/// Paints every tile with the highest-priority [`TileHighlight`] requested
+/// for it, or the default indicator.
+pub fn composite_highlights(
+    query: Query<(Entity, &TileHighlight)>,
+    mut tile_query: Query<(&Coordinates, &Parent, &mut Handle<TileHighlightMaterial>), With<Tile>>,
+    parents_query: Query<&Parent>,
+    grids_query: Query<Entity, With<Grid>>,
+    grid_assets: Res<GridAssets>,
+) {
+    let mut layers = HashMap::<(Option<Entity>, Coordinates), HighlightLayer>::new();
+
+    for (entity, highlight) in query.iter() {
+        let grid = find_parent(entity, &parents_query, &grids_query);
+
+        for tile in highlight.tiles.iter() {
+            layers
+                .entry((grid, *tile))
+                .and_modify(|layer| *layer = (*layer).max(highlight.layer))
+                .or_insert(highlight.layer);
+        }
+    }
+
+    for (coordinates, parent, mut material) in tile_query.iter_mut() {
+        let on_grid = layers.get(&(Some(parent.get()), *coordinates));
+        let anywhere = layers.get(&(None, *coordinates));
+
+        let new_material = match on_grid.max(anywhere) {
+            Some(layer) => grid_assets.layer_material(*layer),
+            None => &grid_assets.default_indicator,
+        };
+
+        if *material != *new_material {
+            *material = new_material.clone();
+        }
+    }
+}
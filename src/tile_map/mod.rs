@@ -1,8 +1,10 @@
 //! The tile map that determines grid-locked interactions, such as operators.
 
-//pub mod focus;
+pub mod effect;
+pub mod focus;
+pub mod highlight;
 pub mod nav;
-//pub mod range;
+pub mod range;
 
 use bevy::prelude::*;
 use bevy::transform::TransformSystem;
@@ -35,9 +37,18 @@ impl Plugin for GridPlugin {
                     cache_tiles,
                     position_gridlocked_entities
                         .before(TransformSystem::TransformPropagate)
-                        .after(cache_tiles)
+                        .after(cache_tiles),
+                    (
+                        highlight::highlight_ranges,
+                        highlight::highlight_deploy_previews,
+                        highlight::highlight_danger,
+                    )
+                        .before(highlight::composite_highlights),
+                    highlight::composite_highlights
+                        .after(setup_new_tiles),
                 )
             )
+            .add_systems(Update, range::face_ranges)
             .add_systems(Startup, load_grid_assets);
             //.add_systems(OnEnter(AppState::StageLoading), load_grid_assets);
     }
@@ -51,10 +62,29 @@ pub struct GridAssets {
     pub square_mesh: Handle<Mesh>,
     /// The grid indicator texture.
     pub grid_indicator_texture: Handle<Image>,
+    /// Material for tiles that aren't highlighted.
+    pub default_indicator: Handle<TileHighlightMaterial>,
     /// Material for hostile (or damage) tiles.
     pub hostile_indicator: Handle<TileHighlightMaterial>,
     /// Material for support (or healing) tiles.
     pub support_indicator: Handle<TileHighlightMaterial>,
+    /// Material for deployable tiles.
+    pub deploy_indicator: Handle<TileHighlightMaterial>,
+    /// Material for tiles enemies can attack.
+    pub danger_indicator: Handle<TileHighlightMaterial>,
+}
+
+impl GridAssets {
+    /// The material for a [`HighlightLayer`][1].
+    ///
+    /// [1]: highlight::HighlightLayer
+    pub fn layer_material(&self, layer: highlight::HighlightLayer) -> &Handle<TileHighlightMaterial> {
+        match layer {
+            highlight::HighlightLayer::Danger => &self.danger_indicator,
+            highlight::HighlightLayer::AttackRange => &self.hostile_indicator,
+            highlight::HighlightLayer::DeployPreview => &self.deploy_indicator,
+        }
+    }
 }
 
 /// Grid bundle.
@@ -91,10 +121,34 @@ impl Deref for CachedTile {
 }
 
 impl Grid {
+    /// Creates a `Grid` that is not backed by tile entities.
+    ///
+    /// Useful for pathfinding on a map that hasn't been spawned. Every tile
+    /// has a [`Entity::PLACEHOLDER`] entity, and later tiles replace earlier
+    /// tiles with the same coordinates.
+    pub fn from_tiles(tiles: impl IntoIterator<Item = (Coordinates, Tile)>) -> Grid {
+        Grid {
+            lookup: tiles
+                .into_iter()
+                .map(|(coordinates, tile)| {
+                    (coordinates, CachedTile {
+                        entity: Entity::PLACEHOLDER,
+                        tile,
+                    })
+                })
+                .collect(),
+        }
+    }
+
     /// Gets a tile from the cache.
     pub fn get(&self, idx: &Coordinates) -> Option<&CachedTile> {
         self.lookup.get(idx)
     }
+
+    /// Iterates over every cached tile, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&Coordinates, &CachedTile)> {
+        self.lookup.iter()
+    }
 }
 
 /// The coordinates to a tile entity.
@@ -122,6 +176,14 @@ impl Coordinates {
             y: local.y.floor() as i32,
         })
     }
+
+    /// The coordinates of the tile that covers the local position.
+    ///
+    /// Tiles are centered on their coordinates, so this is the nearest tile,
+    /// unlike [`Coordinates::from_local`].
+    pub fn nearest(local: Vec3) -> Coordinates {
+        Coordinates::from_local(local + Vec3::new(0.5, 0.5, 0.0))
+    }
 }
 
 impl From<IVec2> for Coordinates {
@@ -166,12 +228,36 @@ impl Add for Coordinates {
 pub struct Tile {
     kind: TileKind,
     deployable: bool,
+    ranged_deployable: bool,
+    effects: Vec<TileEffect>,
 }
 
 impl Tile {
     /// Creates a new tile.
     pub fn new(kind: TileKind, deployable: bool) -> Tile {
-        Tile { kind, deployable }
+        Tile {
+            kind,
+            deployable,
+            ranged_deployable: false,
+            effects: Vec::new(),
+        }
+    }
+
+    /// Constructs a `Tile` that allows ranged operators to deploy on it,
+    /// even if it is [`TileKind::Ground`].
+    pub fn with_ranged_deployment(self, ranged_deployable: bool) -> Tile {
+        Tile {
+            ranged_deployable,
+            ..self
+        }
+    }
+
+    /// Constructs a `Tile` with effects applied to units standing on it.
+    pub fn with_effects(self, effects: impl Into<Vec<TileEffect>>) -> Tile {
+        Tile {
+            effects: effects.into(),
+            ..self
+        }
     }
 
     /// The kind of tile.
@@ -179,17 +265,81 @@ impl Tile {
         self.kind
     }
 
+    /// Changes the kind of tile.
+    pub fn set_kind(&mut self, kind: TileKind) {
+        self.kind = kind;
+    }
+
+    /// Marks the tile deployable or undeployable.
+    pub fn set_deployable(&mut self, deployable: bool) {
+        self.deployable = deployable;
+    }
+
+    /// The effects applied to units standing on the tile.
+    pub fn effects(&self) -> &[TileEffect] {
+        &self.effects
+    }
+
+    /// Whether the tile was marked deployable, regardless of its kind.
+    ///
+    /// See [`Tile::deployable_for`] to check if an operator can actually
+    /// deploy here.
+    pub fn allows_deployment(&self) -> bool {
+        self.deployable
+    }
+
+    /// Whether the tile was marked deployable for ranged operators, even if
+    /// it is [`TileKind::Ground`].
+    pub fn allows_ranged_deployment(&self) -> bool {
+        self.ranged_deployable
+    }
+
     /// Whether the tile is solid.
     ///
-    /// This checks if [`Tile::kind`] returns [`TileKind::Ground`] and returns
-    /// `true` if it does.
+    /// Solid tiles block enemy movement. This is true for
+    /// [`TileKind::HighGround`] and [`TileKind::Wall`].
     pub fn is_solid(&self) -> bool {
-        self.kind == TileKind::HighGround
+        matches!(self.kind, TileKind::HighGround | TileKind::Wall)
+    }
+
+    /// Whether enemies can path across the tile.
+    ///
+    /// Enemies can be forced onto tiles they would never walk onto, like
+    /// [`TileKind::Hole`], but the pathfinder will never pick them.
+    pub fn is_walkable(&self) -> bool {
+        !self.is_solid() && !self.is_fatal()
+    }
+
+    /// Whether units that end up on the tile die instantly.
+    pub fn is_fatal(&self) -> bool {
+        self.kind == TileKind::Hole
     }
 
-    /// Whether the tile is deployable or not.
+    /// Whether the tile is deployable by any kind of operator.
     pub fn deployable(&self) -> bool {
-        self.deployable
+        self.deployable_for(DeployClass::Melee) || self.deployable_for(DeployClass::Ranged)
+    }
+
+    /// Whether an operator of a [`DeployClass`] can deploy on this tile.
+    pub fn deployable_for(&self, class: DeployClass) -> bool {
+        if !self.deployable {
+            return false;
+        }
+
+        match (self.kind, class) {
+            (TileKind::Ground, DeployClass::Melee) => true,
+            (TileKind::Ground, DeployClass::Ranged) => self.ranged_deployable,
+            (TileKind::HighGround, DeployClass::Ranged) => true,
+            _ => false,
+        }
+    }
+
+    /// The height units standing on the tile are placed at.
+    pub fn height(&self) -> f32 {
+        match self.kind {
+            TileKind::HighGround | TileKind::Wall => HIGH_GROUND_HEIGHT,
+            TileKind::Ground | TileKind::Hole => 0.0,
+        }
     }
 }
 
@@ -199,9 +349,40 @@ impl Tile {
 /// cross.
 #[derive(Clone, Copy, Component, Debug, Default, Deserialize, PartialEq, Eq, Reflect, Serialize)]
 pub enum TileKind {
+    /// Walkable ground. Melee operators deploy here.
     Ground,
+    /// Raised ground that enemies cannot cross. Ranged operators deploy here.
     #[default]
     HighGround,
+    /// An impassable tile that no operator can deploy on.
+    Wall,
+    /// A pit. Enemies will not path across it, but any unit that is forced
+    /// onto it falls and dies instantly.
+    Hole,
+}
+
+/// The class of an operator, which determines which tiles it can be deployed
+/// on.
+#[derive(Clone, Copy, Component, Debug, Default, Deserialize, PartialEq, Eq, Hash, Reflect, Serialize)]
+pub enum DeployClass {
+    /// Deploys on [`TileKind::Ground`].
+    #[default]
+    Melee,
+    /// Deploys on [`TileKind::HighGround`].
+    Ranged,
+}
+
+/// An effect a tile has on units standing on it.
+///
+/// See the [`effect`] module for the systems that apply these.
+#[derive(Clone, Debug, Deserialize, PartialEq, Reflect, Serialize)]
+pub enum TileEffect {
+    /// Reduces movement speed by a fraction, e.g. `Slow(0.4)` is 40% slower.
+    Slow(f32),
+    /// Heals units by an amount of HP every second.
+    Heal(f32),
+    /// Deals true damage to units every second.
+    Damage(f32),
 }
 
 /// A tile bundle for setting up a [`Tile`].
@@ -226,11 +407,10 @@ pub fn position_gridlocked_entities(
     for (parent, mut transform, coordinates) in query.iter_mut() {
         let grid = grid_query.get(parent.get()).unwrap();
 
-        let height = match grid.get(coordinates).map(|t| t.tile.kind) {
-            Some(TileKind::Ground) => 0.0,
-            Some(TileKind::HighGround) => HIGH_GROUND_HEIGHT,
-            None => 0.0,
-        };
+        let height = grid
+            .get(coordinates)
+            .map(|t| t.height())
+            .unwrap_or_default();
 
         *transform = Transform::from_translation(coordinates.local(height));
     }
@@ -264,7 +444,7 @@ pub fn setup_new_tiles(
         *mesh = grid_assets.square_mesh.clone();
 
         // default material
-        *_material = grid_assets.hostile_indicator.clone();
+        *_material = grid_assets.default_indicator.clone();
     }
 }
 
@@ -282,6 +462,12 @@ pub fn load_grid_assets(
     grid_assets.grid_indicator_texture = asset_server.load("system/grid_indicator.png");
 
     // create materials
+    grid_assets.default_indicator = tile_materials.add(TileHighlightMaterial {
+        color: Color::rgba(1.0, 1.0, 1.0, 0.15),
+        color_texture: Some(grid_assets.grid_indicator_texture.clone()),
+        animate_speed: 0.0,
+    });
+
     grid_assets.hostile_indicator = tile_materials.add(TileHighlightMaterial {
         color: Color::rgba(1.0, 0.576, 0.180, 0.9), // #ff932e
         color_texture: Some(grid_assets.grid_indicator_texture.clone()),
@@ -293,5 +479,17 @@ pub fn load_grid_assets(
         color_texture: Some(grid_assets.grid_indicator_texture.clone()),
         animate_speed: 0.25,
     });
+
+    grid_assets.deploy_indicator = tile_materials.add(TileHighlightMaterial {
+        color: Color::rgba(0.380, 0.851, 0.420, 0.6), // #61d96b
+        color_texture: Some(grid_assets.grid_indicator_texture.clone()),
+        animate_speed: 0.0,
+    });
+
+    grid_assets.danger_indicator = tile_materials.add(TileHighlightMaterial {
+        color: Color::rgba(0.851, 0.192, 0.192, 0.4), // #d93131
+        color_texture: Some(grid_assets.grid_indicator_texture.clone()),
+        animate_speed: 0.0,
+    });
 }
 
@@ -103,6 +103,24 @@ impl CalculatedPath {
     fn pop_waypoint(&mut self) {
         self.waypoints.pop_front();
     }
+
+    /// The waypoints left to steer through, in world space.
+    pub fn waypoints(&self) -> impl Iterator<Item = Vec3> + '_ {
+        self.waypoints.iter().copied()
+    }
+
+    /// The distance left to travel from `from` through every remaining
+    /// waypoint.
+    pub fn distance_from(&self, from: Vec3) -> f32 {
+        self.waypoints
+            .iter()
+            .scan(from, |last, next| {
+                let distance = last.distance(*next);
+                *last = *next;
+                Some(distance)
+            })
+            .sum()
+    }
 }
 
 /// A pathfinder for a [`Grid`].
@@ -118,8 +136,8 @@ impl<'a> Pathfinder<'a> {
         }
     }
 
-    /// Finds a path between two [`TileKind::Ground`][1] tiles using the A*
-    /// algorithm.
+    /// Finds a path between two walkable [`TileKind::Ground`][1] tiles using
+    /// the A* algorithm.
     ///
     /// Assumes the starting node is a valid node.
     ///
@@ -171,7 +189,7 @@ impl<'a> Pathfinder<'a> {
                 let tile = self.grid.get(&neighbor.into());
 
                 if let Some(tile) = tile {
-                    if !tile.is_solid() {
+                    if tile.is_walkable() {
                         // add neighbor to open list
                         open.push(GridNode {
                             pos: neighbor,
@@ -2,7 +2,12 @@
 
 use bevy::prelude::*;
 
+use serde::{Deserialize, Serialize};
+
 use super::Coordinates;
+use super::highlight::HighlightLayer;
+
+use crate::find_parent;
 
 /// A range.
 #[derive(Clone, Component, Debug, Default)]
@@ -37,16 +42,30 @@ impl Range {
         let diff = self.direction.difference(direction);
 
         for tile in self.tiles.iter_mut() {
-            tile.x = tile.x * diff.cos() - tile.y * diff.sin();
-            tile.y = tile.x * diff.sin() + tile.y * diff.cos();
+            let Coordinates(IVec2 { x, y }) = *tile;
+
+            tile.x = x * diff.cos() - y * diff.sin();
+            tile.y = x * diff.sin() + y * diff.cos();
         }
 
         self.direction = direction;
     }
 }
 
+/// The direction an operator (and its [`Range`]) was deployed facing.
+///
+/// Every [`Range`] on the entity or its descendants is turned to face this
+/// direction.
+#[derive(Clone, Copy, Component, Debug, Default, PartialEq, Eq)]
+pub struct Facing(pub Direction);
+
+/// Marks gridlocked entities whose [`Range`] should be highlighted on the
+/// grid, and on which [`HighlightLayer`].
+#[derive(Clone, Component, Debug, Default)]
+pub struct HighlightRange(pub HighlightLayer);
+
 /// A direction for a range.
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Hash, Reflect, Serialize)]
 pub enum Direction {
     #[default]
     Right,
@@ -58,7 +77,7 @@ pub enum Direction {
 impl Direction {
     /// How many 90-degree turns one direction is from another.
     pub fn difference(self, other: Direction) -> Direction {
-        Direction::from_turn_count(self.turn_count() - other.turn_count())
+        Direction::from_turn_count(other.turn_count() - self.turn_count())
     }
 
     pub fn sin(self) -> i32 {
@@ -79,6 +98,11 @@ impl Direction {
         }
     }
 
+    /// The counter-clockwise angle from [`Direction::Right`] in radians.
+    pub fn to_radians(self) -> f32 {
+        self.turn_count() as f32 * std::f32::consts::FRAC_PI_2
+    }
+
     fn turn_count(self) -> i32 {
         match self {
             Direction::Right => 0,
@@ -89,7 +113,7 @@ impl Direction {
     }
 
     fn from_turn_count(i: i32) -> Direction {
-        match i % 4 {
+        match i.rem_euclid(4) {
             0 => Direction::Right,
             1 => Direction::Up,
             2 => Direction::Left,
@@ -99,3 +123,20 @@ impl Direction {
     }
 }
 
+/// Turns every [`Range`] to face the [`Facing`] of its entity or closest
+/// ancestor.
+pub fn face_ranges(
+    mut query: Query<(Entity, &mut Range)>,
+    parents_query: Query<&Parent>,
+    facing_query: Query<&Facing>,
+) {
+    for (entity, mut range) in query.iter_mut() {
+        let Some(facing) = find_parent(entity, &parents_query, &facing_query) else {
+            continue;
+        };
+
+        if range.direction() != facing.0 {
+            range.face_to(facing.0);
+        }
+    }
+}
@@ -1,10 +1,23 @@
 //! Core UI and display functionality.
 
+pub mod info_panel;
+
 use bevy::prelude::*;
 use bevy::transform::TransformSystem;
 
-use crate::battle::damage::Health;
+use crate::battle::damage::{Dead, Health};
+use crate::battle::path::Hidden;
+use crate::battle::skill::{AutoSkillActivation, OverflowBehavior, Skill};
 use crate::battle::Hostility;
+use crate::tile_map::DeployClass;
+
+use std::collections::HashSet;
+
+/// The width of a status bar, in pixels.
+const STATUS_BAR_WIDTH: f32 = 56.0;
+
+const SP_BAR_COLOR: Color = Color::rgb(0.6, 0.9, 0.2);
+const SP_BAR_LOCKOUT_COLOR: Color = Color::rgb(1.0, 0.6, 0.1);
 
 /// The core UI plugin.
 pub struct UiPlugin;
@@ -12,12 +25,20 @@ pub struct UiPlugin;
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
         app
+            .add_systems(Startup, info_panel::create_info_panel)
+            .add_systems(Update, info_panel::update_info_panel)
             .add_systems(PostUpdate,
                 (
-                    (create_status_bar, cleanup_status_bar)
+                    (
+                        (create_status_bar, apply_deferred, create_skill_bar).chain(),
+                        cleanup_skill_bar,
+                        cleanup_status_bar,
+                    )
                         .before(bevy::ui::UiSystem::Layout),
                     (
                         sync_health_bar,
+                        sync_skill_bar,
+                        hide_status_bar_for_hidden_entities,
                         sync_status_bar_position
                             .after(TransformSystem::TransformPropagate),
                     )
@@ -70,6 +91,31 @@ impl HealthBar {
     }
 }
 
+/// The node containing everything displayed for a [`Skill`] on a status
+/// bar.
+#[derive(Debug, Component, Clone)]
+struct SkillBarRoot {
+    skill: Entity,
+}
+
+/// The SP bar of a [`Skill`].
+#[derive(Debug, Component, Clone)]
+struct SkillBar {
+    skill: Entity,
+}
+
+/// The charge count of a [`Skill`] that charges.
+#[derive(Debug, Component, Clone)]
+struct SkillChargeText {
+    skill: Entity,
+}
+
+/// Shown when a manually activated [`Skill`] can be activated.
+#[derive(Debug, Component, Clone)]
+struct SkillReadyIcon {
+    skill: Entity,
+}
+
 fn cleanup_status_bar(
     mut commands: Commands,
     query: Query<(Entity, &StatusBar)>,
@@ -120,6 +166,101 @@ fn sync_health_bar(
     }
 }
 
+fn sync_skill_bar(
+    mut skill_bar_query: Query<(&SkillBar, &mut Style, &mut BackgroundColor)>,
+    mut charge_text_query: Query<(&SkillChargeText, &mut Text)>,
+    mut ready_icon_query: Query<(&SkillReadyIcon, &mut Visibility)>,
+    skill_query: Query<(&Skill, Option<&AutoSkillActivation>)>,
+) {
+    for (skill_bar, mut style, mut color) in skill_bar_query.iter_mut() {
+        let Ok((skill, _)) = skill_query.get(skill_bar.skill) else {
+            continue;
+        };
+
+        // charging skills show the progress towards the next charge
+        let percentage = match skill.overflow() {
+            OverflowBehavior::Charge(charges) if skill.charges() < charges.get() => {
+                skill.percentage().fract()
+            }
+            _ => skill.percentage().min(1.0),
+        };
+
+        let width = Val::Percent(percentage * 100.0);
+
+        if style.width != width {
+            style.width = width;
+        }
+
+        let new_color = if skill.sp_lockout() {
+            SP_BAR_LOCKOUT_COLOR
+        } else {
+            SP_BAR_COLOR
+        };
+
+        if color.0 != new_color {
+            color.0 = new_color;
+        }
+    }
+
+    for (charge_text, mut text) in charge_text_query.iter_mut() {
+        let Ok((skill, _)) = skill_query.get(charge_text.skill) else {
+            continue;
+        };
+
+        let charges = match skill.overflow() {
+            OverflowBehavior::Charge(_) if skill.charges() > 0 => skill.charges().to_string(),
+            _ => String::new(),
+        };
+
+        if text.sections[0].value != charges {
+            text.sections[0].value = charges;
+        }
+    }
+
+    for (ready_icon, mut visibility) in ready_icon_query.iter_mut() {
+        let Ok((skill, auto)) = skill_query.get(ready_icon.skill) else {
+            continue;
+        };
+
+        let ready = auto.is_none() && !skill.sp_lockout() && skill.charges() > 0;
+
+        let new_visibility = if ready {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+
+        if *visibility != new_visibility {
+            *visibility = new_visibility;
+        }
+    }
+}
+
+/// Hides status bars of entities that are [`Hidden`], and shows them again
+/// when they reappear.
+pub fn hide_status_bar_for_hidden_entities(
+    mut query: Query<(&StatusBar, &mut Style)>,
+    hidden_query: Query<(), With<Hidden>>,
+    dead_query: Query<(), With<Dead>>,
+) {
+    for (status_bar, mut style) in query.iter_mut() {
+        // dead entities are handled by the damage systems
+        if dead_query.contains(status_bar.entity) {
+            continue;
+        }
+
+        let display = if hidden_query.contains(status_bar.entity) {
+            Display::None
+        } else {
+            Display::Flex
+        };
+
+        if style.display != display {
+            style.display = display;
+        }
+    }
+}
+
 pub fn sync_status_bar_position(
     mut query: Query<(&StatusBar, &mut Style)>,
     position_query: Query<&GlobalTransform>,
@@ -171,9 +312,9 @@ pub fn create_status_bar(
                             style: Style {
                                 // TODO: how big should status bars be?
                                 height: Val::Px(4.0),
-                                width: Val::Px(56.0),
+                                width: Val::Px(STATUS_BAR_WIDTH),
                                 top: Val::Px(8.0),
-                                left: Val::Px(-56.0 / 2.0),
+                                left: Val::Px(-STATUS_BAR_WIDTH / 2.0),
                                 position_type: PositionType::Absolute,
                                 ..default()
                             },
@@ -217,3 +358,229 @@ pub fn create_status_bar(
             });
     }
 }
+
+/// Creates SP bars for the [`Skill`]s of operators, under their health bar.
+///
+/// The bar is attached to the operator's [`StatusBar`], so this picks up
+/// both new skills and new status bars.
+pub fn create_skill_bar(
+    mut commands: Commands,
+    skill_query: Query<(Entity, &Parent), Added<Skill>>,
+    status_bar_query: Query<(Entity, &StatusBar)>,
+    new_status_bar_query: Query<(Entity, &StatusBar), Added<StatusBar>>,
+    children_query: Query<&Children>,
+    skills_query: Query<(), With<Skill>>,
+    operator_query: Query<(), With<DeployClass>>,
+) {
+    let mut new_skills = HashSet::new();
+
+    // skills added to units that already have a status bar
+    for (skill, parent) in skill_query.iter() {
+        let Some((status_bar, _)) = status_bar_query
+            .iter()
+            .find(|(_, status_bar)| status_bar.entity == parent.get())
+        else {
+            continue;
+        };
+
+        new_skills.insert((status_bar, parent.get(), skill));
+    }
+
+    // units that just got a status bar
+    for (status_bar, StatusBar { entity }) in new_status_bar_query.iter() {
+        let skills = children_query
+            .get(*entity)
+            .into_iter()
+            .flat_map(|children| children.iter())
+            .filter(|child| skills_query.contains(**child));
+
+        for &skill in skills {
+            new_skills.insert((status_bar, *entity, skill));
+        }
+    }
+
+    for (status_bar, entity, skill) in new_skills {
+        if !operator_query.contains(entity) {
+            continue;
+        }
+
+        commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        ..default()
+                    },
+                    ..default()
+                },
+                SkillBarRoot { skill },
+            ))
+            .set_parent(status_bar)
+            .with_children(|parent| {
+                parent
+                    .spawn((
+                        NodeBundle {
+                            style: Style {
+                                height: Val::Px(3.0),
+                                width: Val::Px(STATUS_BAR_WIDTH),
+                                top: Val::Px(13.0),
+                                left: Val::Px(-STATUS_BAR_WIDTH / 2.0),
+                                position_type: PositionType::Absolute,
+                                ..default()
+                            },
+                            background_color: Color::BLACK.into(),
+                            ..default()
+                        },
+                    ))
+                    .with_children(|parent| {
+                        parent
+                            .spawn((
+                                NodeBundle {
+                                    style: Style {
+                                        height: Val::Percent(100.0),
+                                        width: Val::Percent(0.0),
+                                        position_type: PositionType::Absolute,
+                                        ..default()
+                                    },
+                                    background_color: SP_BAR_COLOR.into(),
+                                    ..default()
+                                },
+                                SkillBar { skill },
+                            ));
+                    });
+
+                parent
+                    .spawn((
+                        TextBundle {
+                            style: Style {
+                                top: Val::Px(9.0),
+                                left: Val::Px(STATUS_BAR_WIDTH / 2.0 + 2.0),
+                                position_type: PositionType::Absolute,
+                                ..default()
+                            },
+                            ..TextBundle::from_section(
+                                "",
+                                TextStyle {
+                                    font_size: 10.0,
+                                    color: Color::WHITE,
+                                    ..default()
+                                },
+                            )
+                        },
+                        SkillChargeText { skill },
+                    ));
+
+                parent
+                    .spawn((
+                        NodeBundle {
+                            style: Style {
+                                height: Val::Px(8.0),
+                                width: Val::Px(8.0),
+                                top: Val::Px(8.0),
+                                left: Val::Px(-STATUS_BAR_WIDTH / 2.0 - 10.0),
+                                position_type: PositionType::Absolute,
+                                ..default()
+                            },
+                            background_color: Color::YELLOW.into(),
+                            visibility: Visibility::Hidden,
+                            ..default()
+                        },
+                        SkillReadyIcon { skill },
+                    ));
+            });
+    }
+}
+
+/// Removes the SP bars of [`Skill`]s that were removed.
+///
+/// SP bars of units that are gone are removed with their [`StatusBar`].
+pub fn cleanup_skill_bar(
+    mut commands: Commands,
+    query: Query<(Entity, &SkillBarRoot)>,
+    mut removed: RemovedComponents<Skill>,
+) {
+    for skill in removed.iter() {
+        for (entity, _) in query.iter().filter(|(_, root)| root.skill == skill) {
+            commands
+                .entity(entity)
+                .despawn_recursive();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+
+        app.add_systems(
+            Update,
+            (
+                create_status_bar,
+                apply_deferred,
+                create_skill_bar,
+                cleanup_skill_bar,
+                cleanup_status_bar,
+            ).chain(),
+        );
+
+        app
+    }
+
+    fn spawn_unit(app: &mut App, operator: bool) -> (Entity, Entity) {
+        let mut unit = app.world.spawn((Health::default(), GlobalTransform::default()));
+
+        if operator {
+            unit.insert(DeployClass::default());
+        }
+
+        let unit = unit.id();
+        let skill = app.world.spawn(Skill::default()).set_parent(unit).id();
+
+        (unit, skill)
+    }
+
+    fn count<T: Component>(app: &mut App) -> usize {
+        app.world.query::<&T>().iter(&app.world).count()
+    }
+
+    #[test]
+    fn skill_bars_only_on_operators() {
+        let mut app = app();
+
+        spawn_unit(&mut app, true);
+        spawn_unit(&mut app, false);
+
+        app.update();
+
+        assert_eq!(count::<StatusBar>(&mut app), 2);
+        assert_eq!(count::<SkillBarRoot>(&mut app), 1);
+    }
+
+    #[test]
+    fn no_dangling_skill_bars() {
+        let mut app = app();
+
+        let (unit, skill) = spawn_unit(&mut app, true);
+
+        app.update();
+
+        assert_eq!(count::<SkillBarRoot>(&mut app), 1);
+
+        // removing the skill removes its bar, but not the status bar
+        app.world.entity_mut(skill).despawn_recursive();
+        app.update();
+
+        assert_eq!(count::<SkillBarRoot>(&mut app), 0);
+        assert_eq!(count::<StatusBar>(&mut app), 1);
+
+        // tearing down the unit removes everything
+        app.world.entity_mut(unit).despawn_recursive();
+        app.update();
+
+        assert_eq!(count::<StatusBar>(&mut app), 0);
+        assert_eq!(count::<Node>(&mut app), 0);
+    }
+}
@@ -0,0 +1,198 @@
+//! A side panel showing details about the focused unit.
+
+use bevy::prelude::*;
+
+use std::fmt::Write;
+
+use crate::battle::damage::Health;
+use crate::battle::skill::{Skill, SkillLockoutTimer};
+use crate::battle::targeting::Targets;
+use crate::stats::{stat, ComputedStat};
+use crate::status::StatusEffect;
+use crate::tile_map::focus::Focus;
+
+const PANEL_WIDTH: f32 = 260.0;
+const FONT_SIZE: f32 = 16.0;
+
+/// The root node of the info panel.
+#[derive(Clone, Component, Debug, Default)]
+pub struct InfoPanel;
+
+/// The text of the info panel.
+#[derive(Clone, Component, Debug, Default)]
+pub struct InfoPanelText;
+
+/// The stats shown on the info panel.
+type StatQuery<'w, 's> = Query<'w, 's, (
+    Option<&'static ComputedStat<stat::MaxHp>>,
+    Option<&'static ComputedStat<stat::Atk>>,
+    Option<&'static ComputedStat<stat::Def>>,
+    Option<&'static ComputedStat<stat::Res>>,
+    Option<&'static ComputedStat<stat::Aspd>>,
+    Option<&'static ComputedStat<stat::Block>>,
+)>;
+
+pub fn create_info_panel(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Px(PANEL_WIDTH),
+                    height: Val::Percent(100.0),
+                    right: Val::Px(0.0),
+                    top: Val::Px(0.0),
+                    padding: UiRect::all(Val::Px(12.0)),
+                    position_type: PositionType::Absolute,
+                    display: Display::None,
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            InfoPanel,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: FONT_SIZE,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                ),
+                InfoPanelText,
+            ));
+        });
+}
+
+/// Shows the info panel while a unit is focused, and keeps it up to date.
+pub fn update_info_panel(
+    mut panel_query: Query<&mut Style, With<InfoPanel>>,
+    mut text_query: Query<&mut Text, With<InfoPanelText>>,
+    focus_query: Query<Entity, With<Focus>>,
+    name_query: Query<&Name>,
+    health_query: Query<&Health>,
+    stat_query: StatQuery,
+    targets_query: Query<&Targets>,
+    children_query: Query<&Children>,
+    skill_query: Query<(&Skill, Option<&SkillLockoutTimer>)>,
+    status_query: Query<&StatusEffect>,
+) {
+    let focused = focus_query.iter().next();
+
+    for mut style in panel_query.iter_mut() {
+        let display = if focused.is_some() {
+            Display::Flex
+        } else {
+            Display::None
+        };
+
+        if style.display != display {
+            style.display = display;
+        }
+    }
+
+    let Some(entity) = focused else {
+        return;
+    };
+
+    let name = |entity: Entity| match name_query.get(entity) {
+        Ok(name) => name.to_string(),
+        Err(_) => format!("{:?}", entity),
+    };
+
+    // writing to a String never fails
+    let mut s = String::new();
+
+    let _ = writeln!(s, "{}", name(entity));
+    let _ = writeln!(s);
+
+    if let Ok(health) = health_query.get(entity) {
+        match stat_query.get(entity).ok().and_then(|(max_hp, ..)| max_hp) {
+            Some(max_hp) => {
+                let _ = writeln!(s, "HP: {:.0}/{}", health.get(), max_hp.get());
+            }
+            None => {
+                let _ = writeln!(s, "HP: {:.0}", health.get());
+            }
+        }
+    }
+
+    if let Ok((_, atk, def, res, aspd, block)) = stat_query.get(entity) {
+        let stats = [
+            ("ATK", atk.map(|s| s.get())),
+            ("DEF", def.map(|s| s.get())),
+            ("RES", res.map(|s| s.get())),
+            ("ASPD", aspd.map(|s| s.get())),
+            ("Block", block.map(|s| s.get())),
+        ];
+
+        for (label, value) in stats {
+            if let Some(value) = value {
+                let _ = writeln!(s, "{}: {}", label, value);
+            }
+        }
+    }
+
+    let children = children_query
+        .get(entity)
+        .map(|children| children.iter().copied().collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    let skills = children
+        .iter()
+        .filter_map(|&child| skill_query.get(child).ok().map(|skill| (child, skill)))
+        .collect::<Vec<_>>();
+
+    if !skills.is_empty() {
+        let _ = writeln!(s);
+        let _ = writeln!(s, "Skills:");
+
+        for (child, (skill, lockout)) in skills {
+            let _ = write!(s, "  {}: {:.0}/{:.0} SP", name(child), skill.sp(), skill.max_sp());
+
+            if skill.sp_lockout() {
+                let progress = lockout.map(|lockout| lockout.progress()).unwrap_or_default();
+                let _ = write!(s, " (lockout {:.0}%)", progress * 100.0);
+            }
+
+            let _ = writeln!(s);
+        }
+    }
+
+    let statuses = children
+        .iter()
+        .filter_map(|&child| status_query.get(child).ok())
+        .collect::<Vec<_>>();
+
+    if !statuses.is_empty() {
+        let _ = writeln!(s);
+        let _ = writeln!(s, "Status:");
+
+        for status in statuses {
+            let _ = writeln!(s, "  {}", status.name);
+        }
+    }
+
+    if let Ok(targets) = targets_query.get(entity) {
+        let _ = writeln!(s);
+        let _ = writeln!(s, "Targets:");
+
+        if targets.is_empty() {
+            let _ = writeln!(s, "  none");
+        }
+
+        for &target in targets.iter() {
+            let _ = writeln!(s, "  {}", name(target));
+        }
+    }
+
+    for mut text in text_query.iter_mut() {
+        // avoid triggering a relayout every frame
+        if text.sections[0].value != s {
+            text.sections[0].value = s.clone();
+        }
+    }
+}